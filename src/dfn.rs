@@ -0,0 +1,480 @@
+use crate::error::{Error, Result};
+use crate::math::numerical_methods::{gaussian_elimination, TimeScheme};
+use crate::model::{
+    check_electrolyte_stability, check_input, check_particle_stability, check_surface_stoichiometry,
+    electrolyte_mesh, exchange_current_density, solve_fixed, Electrode, Electrolyte, FixedStepping, Particle, Region,
+    SPMeModel, Separator, CATION_TRANSFERENCE_NUMBER, FARADAY, GAS_CONSTANT, STANDARD_TEMPERATURE,
+};
+use crate::observer::{Observer, State};
+use crate::solution::{ElectrodeSample, OutputSpecification, Sample, Solution};
+use crate::Simulate;
+
+// Doyle-Fuller-Newman (pseudo two dimensional) model. A particle is resolved at every electrolyte node of the
+// electrodes, and the solid and electrolyte potentials through the cell are solved as coupled algebraic equations at
+// every timestep. Each electrode uses its primary particle, open circuit potential and kinetic law with quasi-static
+// kinetics, so double layers, blends, SEI, hysteresis and the thermal model are not supported, and an SPMe model that
+// uses them can't be converted, nor can the DFN model be run with them set on its electrodes. Serves as a reference for high rates, where the uniform reaction through each electrode assumed
+// by the SPMe breaks down.
+
+#[derive(Debug, Clone)]
+pub struct DFNModel {
+    pub negative_electrode: Electrode,
+    pub positive_electrode: Electrode,
+    pub separator: Separator,
+    pub electrolyte: Electrolyte,
+    pub time_scheme: TimeScheme,
+    pub contact_resistance: f64,           // Ohm, lumped contact and tab resistance
+    pub negative_particles: Vec<Particle>, // one per electrolyte node, from the current collector
+    pub positive_particles: Vec<Particle>, // one per electrolyte node, from the separator
+    potentials: Vec<f64>, // V, last solution for the electrolyte and solid potentials, the next initial guess
+}
+
+// A reaction site, one for every electrolyte node in an electrode
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    region: Region,
+    particle: usize,               // index into the particles of the electrode
+    electrolyte: usize,            // index of the electrolyte potential and concentration
+    solid: usize,                  // index of the solid potential
+    width: f64,                    // m
+    specific_area: f64,            // m^2/m^3
+    open_circuit_potential: f64,   // V
+    exchange_current_density: f64, // A/m^2
+}
+
+impl TryFrom<SPMeModel> for DFNModel {
+    type Error = Error;
+
+    fn try_from(model: SPMeModel) -> Result<Self> {
+        // Takes the cell and mesh of an SPMe model, where every node starts from the particle of its electrode
+        model.check_quasi_static("DFN model")?;
+        check_hysteresis(&model.negative_electrode)?;
+        check_hysteresis(&model.positive_electrode)?;
+        let [nodes_n, _, nodes_p] = model.electrolyte.nodes;
+        Ok(DFNModel {
+            negative_particles: vec![model.negative_electrode.particle.clone(); nodes_n],
+            positive_particles: vec![model.positive_electrode.particle.clone(); nodes_p],
+            negative_electrode: model.negative_electrode,
+            positive_electrode: model.positive_electrode,
+            separator: model.separator,
+            electrolyte: model.electrolyte,
+            time_scheme: model.time_scheme,
+            contact_resistance: model.contact_resistance,
+            potentials: Vec::new(),
+        })
+    }
+}
+
+fn check_hysteresis(electrode: &Electrode) -> Result<()> {
+    // The hysteresis state belongs to the primary particle, while every node of the DFN model has its own
+    if electrode.hysteresis.is_some() {
+        return Err(Error::InvalidParameter { parameter: "DFN model", reason: "hysteresis is not supported" });
+    }
+    Ok(())
+}
+
+impl Default for DFNModel {
+    fn default() -> Self {
+        DFNModel::try_from(SPMeModel::quasi_static()).expect("The default SPMe model only uses parts of the DFN model")
+    }
+}
+
+fn nearest_particles(particles: &[Particle], electrode: &Electrode, nodes: usize) -> Vec<Particle> {
+    // Particle of the nearest node on a different number of equally spaced nodes spanning the same electrode, or
+    // the particle of the electrode without any nodes
+    let n: usize = particles.len();
+    if n == 0 {
+        return vec![electrode.particle.clone(); nodes];
+    }
+    (0..nodes)
+        .map(|i| {
+            let x: f64 = if nodes == 1 { 0.0 } else { i as f64 * (n - 1) as f64 / (nodes - 1) as f64 };
+            particles[x.round() as usize].clone()
+        })
+        .collect()
+}
+
+impl DFNModel {
+    pub fn set_discretisation(&mut self, nodes: [usize; 3]) -> Result<()> {
+        // Changes the number of electrolyte nodes in each region, together with the particles at the nodes of the
+        // electrodes, which take the state of the nearest previous node
+        self.electrolyte.set_discretisation(nodes)?;
+        let [nodes_n, _, nodes_p] = nodes;
+        self.negative_particles = nearest_particles(&self.negative_particles, &self.negative_electrode, nodes_n);
+        self.positive_particles = nearest_particles(&self.positive_particles, &self.positive_electrode, nodes_p);
+        self.potentials.clear();
+        Ok(())
+    }
+
+    fn check_discretisation(&self) -> Result<()> {
+        // The particles are public and can be replaced, as can the electrolyte mesh
+        self.electrolyte.check_discretisation()?;
+        let [nodes_n, _, nodes_p] = self.electrolyte.nodes;
+        if self.negative_particles.len() != nodes_n || self.positive_particles.len() != nodes_p {
+            return Err(Error::InvalidParameter {
+                parameter: "DFN discretisation",
+                reason: "every electrolyte node of an electrode needs one particle, see DFNModel::set_discretisation",
+            });
+        }
+        Ok(())
+    }
+
+    fn electrode(&self, region: Region) -> &Electrode {
+        match region {
+            Region::NegativeElectrode => &self.negative_electrode,
+            Region::PositiveElectrode => &self.positive_electrode,
+            Region::Separator => unreachable!("The separator is not an electrode"),
+        }
+    }
+
+    fn particles(&self, region: Region) -> &[Particle] {
+        match region {
+            Region::NegativeElectrode => &self.negative_particles,
+            Region::PositiveElectrode => &self.positive_particles,
+            Region::Separator => unreachable!("The separator has no particles"),
+        }
+    }
+
+    fn nodes(&self) -> Vec<Node> {
+        // The unknowns are the electrolyte potential of every node, followed by the solid potential of every node
+        // of the negative and then the positive electrode
+        let electrolyte_nodes: usize = self.electrolyte.concentration.len();
+        let mut solid: usize = electrolyte_nodes;
+        let mut nodes: Vec<Node> = Vec::new();
+        for region in [Region::NegativeElectrode, Region::PositiveElectrode] {
+            let electrode: &Electrode = self.electrode(region);
+            let particles: &[Particle] = self.particles(region);
+            for (particle, electrolyte) in self.electrolyte.region_nodes(region).enumerate() {
+                let state: &Particle = &particles[particle];
+                let stoichiometry: f64 = state.surface_concentration() / state.concentration_max;
+                nodes.push(Node {
+                    region,
+                    particle,
+                    electrolyte,
+                    solid,
+                    width: electrode.thickness / particles.len() as f64,
+                    specific_area: 3.0 * electrode.active_material_volume_fraction / state.radius,
+                    open_circuit_potential: electrode.open_circuit_potential_at(stoichiometry, STANDARD_TEMPERATURE),
+                    exchange_current_density: exchange_current_density(
+                        &electrode.kinetics,
+                        electrode.reaction_rate_constant,
+                        state,
+                        self.electrolyte.concentration[electrolyte],
+                    ),
+                });
+                solid += 1;
+            }
+        }
+        nodes
+    }
+
+    fn applied_current_density(&self, current: f64) -> f64 {
+        // A/m^2, positive on discharge as in the electrochemical convention of the potential equations
+        -current / (self.negative_electrode.height * self.negative_electrode.width)
+    }
+
+    fn solve_potentials(&self, current: f64, time: f64) -> Result<(Vec<f64>, Vec<f64>)> {
+        // Charge conservation in the electrolyte and in the solid of every node, with reaction current J per
+        // volume, anodic positive:
+        // $\frac{\partial i_e}{\partial x} = J$, $i_e = -\kappa_{eff} \left( \frac{\partial \phi_e}{\partial x}
+        //   - \frac{2 R T (1 - t_+)}{F} \frac{\partial \ln c_e}{\partial x} \right)$
+        // $\frac{\partial i_s}{\partial x} = -J$, $i_s = -\sigma_{eff} \frac{\partial \phi_s}{\partial x}$
+        // The applied current enters the solid at the current collectors and the electrolyte current is zero
+        // there. The equations are solved together by newton iteration with a dense jacobian. The electrolyte
+        // potential of the first node is the reference and replaces its equation, which is redundant as the
+        // others already conserve the total charge. Returns the potentials and the reaction current density at
+        // the particle surface of every node, A/m^2.
+        let (dx, _, diffusivity) =
+            electrolyte_mesh(&self.negative_electrode, &self.separator, &self.positive_electrode, &self.electrolyte);
+        let electrolyte_nodes: usize = dx.len();
+        let nodes: Vec<Node> = self.nodes();
+        let unknowns: usize = electrolyte_nodes + nodes.len();
+        let applied: f64 = self.applied_current_density(current);
+
+        // The conductivity has the same bruggeman correction as the diffusivity
+        let conductivity: Vec<f64> = diffusivity
+            .iter()
+            .map(|d| self.electrolyte.conductivity * d / self.electrolyte.diffusion_coeff)
+            .collect();
+        let diffusion_potential: f64 =
+            2.0 * (1.0 - CATION_TRANSFERENCE_NUMBER) * GAS_CONSTANT * STANDARD_TEMPERATURE / FARADAY;
+        let log_concentration: Vec<f64> = self.electrolyte.concentration.iter().map(|c| c.ln()).collect();
+
+        // Start from the last solution, or from equilibrium at every node
+        let mut x: Vec<f64> = if self.potentials.len() == unknowns {
+            self.potentials.clone()
+        } else {
+            let mut x: Vec<f64> = vec![0.0; unknowns];
+            for node in &nodes {
+                x[node.solid] = node.open_circuit_potential;
+            }
+            x
+        };
+
+        let reaction = |x: &[f64], node: &Node| -> (f64, f64) {
+            let eta: f64 = x[node.solid] - x[node.electrolyte] - node.open_circuit_potential;
+            self.electrode(node.region)
+                .kinetics
+                .current_density(node.exchange_current_density, eta, STANDARD_TEMPERATURE)
+        };
+
+        for _ in 0..50 {
+            let mut residual: Vec<f64> = vec![0.0; unknowns];
+            let mut jacobian: Vec<Vec<f64>> = vec![vec![0.0; unknowns]; unknowns];
+            let mut add_current = |from: usize, to: usize, conductance: f64, offset: f64| {
+                // Current from node `from` to node `to`, driven by the potential difference less an offset
+                let current: f64 = -conductance * (x[to] - x[from] - offset);
+                residual[from] += current;
+                residual[to] -= current;
+                jacobian[from][to] -= conductance;
+                jacobian[from][from] += conductance;
+                jacobian[to][to] += conductance;
+                jacobian[to][from] -= conductance;
+            };
+
+            // Electrolyte current between neighbouring nodes, with conductances in series at the faces
+            for k in 0..electrolyte_nodes - 1 {
+                let conductance: f64 = 1.0 / (0.5 * dx[k] / conductivity[k] + 0.5 * dx[k + 1] / conductivity[k + 1]);
+                let offset: f64 = diffusion_potential * (log_concentration[k + 1] - log_concentration[k]);
+                add_current(k, k + 1, conductance, offset);
+            }
+            // Solid current between neighbouring nodes of the same electrode
+            for pair in nodes.windows(2).filter(|pair| pair[0].region == pair[1].region) {
+                let conductance: f64 = self.electrode(pair[0].region).effective_solid_conductivity() / pair[0].width;
+                add_current(pair[0].solid, pair[1].solid, conductance, 0.0);
+            }
+            // The applied current enters at the negative and leaves at the positive current collector
+            residual[nodes[0].solid] -= applied;
+            residual[nodes[nodes.len() - 1].solid] += applied;
+
+            // Reaction current from the solid into the electrolyte
+            for node in &nodes {
+                let (current_density, derivative) = reaction(&x, node);
+                let area: f64 = node.specific_area * node.width; // m^2 of particle surface per m^2 of electrode
+                residual[node.solid] += area * current_density;
+                residual[node.electrolyte] -= area * current_density;
+                jacobian[node.solid][node.solid] += area * derivative;
+                jacobian[node.solid][node.electrolyte] -= area * derivative;
+                jacobian[node.electrolyte][node.solid] -= area * derivative;
+                jacobian[node.electrolyte][node.electrolyte] += area * derivative;
+            }
+
+            // Reference potential
+            residual[0] = x[0];
+            jacobian[0] = vec![0.0; unknowns];
+            jacobian[0][0] = 1.0;
+
+            let mut delta: Vec<f64> = residual.iter().map(|r| -r).collect();
+            if !gaussian_elimination(&mut jacobian, &mut delta) {
+                break;
+            }
+            // Long steps are shortened, as the reaction current grows exponentially with the overpotential
+            let largest: f64 = delta.iter().fold(0.0, |largest: f64, d| largest.max(d.abs()));
+            let scale: f64 = (0.1 / largest).min(1.0);
+            for (x, delta) in x.iter_mut().zip(&delta) {
+                *x += scale * delta;
+            }
+            if largest < 1e-10 {
+                let reactions: Vec<f64> = nodes.iter().map(|node| reaction(&x, node).0).collect();
+                return Ok((x, reactions));
+            }
+        }
+        Err(Error::NotConverged {
+            solver: "DFN potential solver",
+            time: Some(time),
+        })
+    }
+
+    fn step(&mut self, dt: f64, current: f64, time: f64) -> Result<()> {
+        // Steps the electrolyte and particle concentrations forward by one timestep at constant current, with
+        // the reaction current from the potentials at the start of the step
+        let (potentials, reactions) = self.solve_potentials(current, time)?;
+        self.potentials = potentials;
+        let nodes: Vec<Node> = self.nodes();
+
+        // Of the lithium released by the reaction, the fraction t+ of the ionic current is carried away by migration
+        let (dx, porosity, diffusivity) =
+            electrolyte_mesh(&self.negative_electrode, &self.separator, &self.positive_electrode, &self.electrolyte);
+        let mut source: Vec<f64> = vec![0.0; dx.len()];
+        for (node, reaction) in nodes.iter().zip(&reactions) {
+            source[node.electrolyte] = (1.0 - CATION_TRANSFERENCE_NUMBER) * node.specific_area * reaction / FARADAY;
+        }
+        self.time_scheme.step_finite_volume(
+            &mut self.electrolyte.concentration,
+            &dx,
+            &porosity,
+            &diffusivity,
+            &source,
+            dt,
+        );
+
+        // Anodic current removes lithium through the particle surface
+        let time_scheme: TimeScheme = self.time_scheme;
+        for (node, reaction) in nodes.iter().zip(&reactions) {
+            let particle: &mut Particle = match node.region {
+                Region::NegativeElectrode => &mut self.negative_particles[node.particle],
+                _ => &mut self.positive_particles[node.particle],
+            };
+            time_scheme.step_radial(
+                &mut particle.concentration,
+                particle.dr,
+                dt,
+                particle.diffusion_coeff,
+                particle.radius,
+                reaction / FARADAY, // flux
+            );
+        }
+        Ok(())
+    }
+
+    fn sample(&mut self, current: f64, time: f64, output: &OutputSpecification) -> Result<Sample> {
+        // Solves the potentials at the present state, and splits the terminal voltage into the contributions of
+        // the SPMe by averaging over the nodes of each electrode. The average open circuit potentials and
+        // surface overpotentials give the average potential difference between solid and electrolyte in each
+        // electrode, and the ohmic terms the remaining drops to the current collectors and across the electrolyte.
+        let (potentials, _) = self.solve_potentials(current, time)?;
+        self.potentials = potentials;
+        let x: &[f64] = &self.potentials;
+        let nodes: Vec<Node> = self.nodes();
+        let applied: f64 = self.applied_current_density(current);
+        let diffusion_potential: f64 =
+            2.0 * (1.0 - CATION_TRANSFERENCE_NUMBER) * GAS_CONSTANT * STANDARD_TEMPERATURE / FARADAY;
+
+        let mean = |region: Region, value: &dyn Fn(&Node) -> f64| -> f64 {
+            let values: Vec<f64> = nodes.iter().filter(|node| node.region == region).map(value).collect();
+            values.iter().sum::<f64>() / values.len() as f64
+        };
+        let electrode_sample = |region: Region| {
+            let particles: &[Particle] = self.particles(region);
+            let stoichiometry = |node: &Node, concentration: f64| {
+                concentration / particles[node.particle].concentration_max
+            };
+            let eta: f64 = mean(region, &|node| x[node.solid] - x[node.electrolyte] - node.open_circuit_potential);
+            ElectrodeSample {
+                open_circuit_potential: mean(region, &|node| node.open_circuit_potential),
+                // Model convention, where charge gives a positive overpotential
+                surface_overpotential: if region == Region::NegativeElectrode { eta } else { -eta },
+                surface_stoichiometry: mean(region, &|node| {
+                    stoichiometry(node, particles[node.particle].surface_concentration())
+                }),
+                average_stoichiometry: mean(region, &|node| {
+                    stoichiometry(node, particles[node.particle].average_concentration())
+                }),
+                particle_concentration: if output.particle_concentration {
+                    average_profile(particles)
+                } else {
+                    Vec::new()
+                },
+            }
+        };
+
+        // Solid potential at the current collectors, half a node from the outermost nodes
+        let first: &Node = &nodes[0];
+        let last: &Node = &nodes[nodes.len() - 1];
+        let negative_collector: f64 =
+            x[first.solid] + applied * 0.5 * first.width / self.negative_electrode.effective_solid_conductivity();
+        let positive_collector: f64 =
+            x[last.solid] - applied * 0.5 * last.width / self.positive_electrode.effective_solid_conductivity();
+
+        let solid_n: f64 = mean(Region::NegativeElectrode, &|node| x[node.solid]);
+        let solid_p: f64 = mean(Region::PositiveElectrode, &|node| x[node.solid]);
+        let electrolyte_n: f64 = mean(Region::NegativeElectrode, &|node| x[node.electrolyte]);
+        let electrolyte_p: f64 = mean(Region::PositiveElectrode, &|node| x[node.electrolyte]);
+        let log_concentration = |node: &Node| self.electrolyte.concentration[node.electrolyte].ln();
+        let electrolyte_concentration_overpotential: f64 = diffusion_potential
+            * (mean(Region::PositiveElectrode, &log_concentration) - mean(Region::NegativeElectrode, &log_concentration));
+
+        Ok(Sample {
+            temperature: STANDARD_TEMPERATURE,
+            negative_electrode: electrode_sample(Region::NegativeElectrode),
+            positive_electrode: electrode_sample(Region::PositiveElectrode),
+            electrolyte_concentration_overpotential,
+            solid_ohmic_overpotential: (positive_collector - solid_p) + (solid_n - negative_collector),
+            electrolyte_ohmic_overpotential: electrolyte_p - electrolyte_n - electrolyte_concentration_overpotential,
+            contact_overpotential: current * self.contact_resistance,
+            film_overpotential: 0.0,
+            electrolyte_concentration: if output.electrolyte_concentration {
+                self.electrolyte.concentration.clone()
+            } else {
+                Vec::new()
+            },
+        })
+    }
+
+}
+
+impl FixedStepping for DFNModel {
+    type Input = f64;
+
+    fn time_scheme(&self) -> TimeScheme {
+        self.time_scheme
+    }
+
+    fn check_ftcs_stability(&self, dt: f64) -> Result<()> {
+        for region in [Region::NegativeElectrode, Region::PositiveElectrode] {
+            check_particle_stability(region, self.particles(region), 1.0, dt)?;
+        }
+        let (dx, porosity, diffusivity) =
+            electrolyte_mesh(&self.negative_electrode, &self.separator, &self.positive_electrode, &self.electrolyte);
+        check_electrolyte_stability(&dx, &porosity, &diffusivity, dt)
+    }
+
+    fn step_sample(
+        &mut self,
+        dt: f64,
+        current: f64,
+        _previous_current: f64,
+        time: f64,
+        output: &OutputSpecification,
+    ) -> Result<(f64, Sample)> {
+        self.step(dt, current, time)?;
+        for region in [Region::NegativeElectrode, Region::PositiveElectrode] {
+            check_surface_stoichiometry(region, self.particles(region), time)?;
+        }
+        self.electrolyte.check_depletion(time)?;
+        Ok((current, self.sample(current, time, output)?))
+    }
+
+    fn observe(&self, time: f64, current: f64, sample: &Sample, observer: &mut dyn Observer) -> Result<()> {
+        // Observers see the particle profiles averaged through each electrode
+        observer.observe(&State {
+            time,
+            current,
+            voltage: sample.voltage(),
+            negative_particle_concentration: &average_profile(&self.negative_particles),
+            positive_particle_concentration: &average_profile(&self.positive_particles),
+            electrolyte_concentration: &self.electrolyte.concentration,
+        })
+    }
+}
+
+fn average_profile(particles: &[Particle]) -> Vec<f64> {
+    // Average concentration profile of particles that share a mesh
+    let mut profile: Vec<f64> = vec![0.0; particles[0].concentration.len()];
+    for particle in particles {
+        for (average, concentration) in profile.iter_mut().zip(&particle.concentration) {
+            *average += concentration / particles.len() as f64;
+        }
+    }
+    profile
+}
+
+impl Simulate for DFNModel {
+    fn solve_observed(
+        &mut self,
+        time: &[f64],
+        current: &[f64],
+        output: &OutputSpecification,
+        observer: &mut dyn Observer,
+    ) -> Result<Solution> {
+        check_input(time, current)?;
+        self.check_discretisation()?;
+        for electrode in [&self.negative_electrode, &self.positive_electrode] {
+            electrode.check_quasi_static("DFN model")?;
+            check_hysteresis(electrode)?;
+        }
+        let solution: Solution = solve_fixed(self, time, current, output, observer)?;
+        observer.finish()?;
+        Ok(solution)
+    }
+}
@@ -0,0 +1,99 @@
+use crate::model::Region;
+
+use std::fmt;
+
+// Errors that can occur while setting up or running a simulation
+
+#[derive(Debug)]
+pub enum Error {
+    // The time and current vectors differ in length
+    MismatchedLengths { time: usize, current: usize },
+    // The time and current vectors are empty
+    EmptyInput,
+    // The time vector is not strictly increasing at the given index
+    UnsortedTime { index: usize },
+    // A time or current sample is NaN or infinite at the given index
+    NonFiniteInput { index: usize },
+    // The explicit time scheme is unstable for the given timestep
+    UnstableScheme { component: &'static str, timestep: f64 },
+    // The surface stoichiometry of a particle left [0, 1], typically from running a cell past full or empty
+    StoichiometryOutOfBounds { region: Region, stoichiometry: f64, time: f64 },
+    // The electrolyte concentration in a region reached zero, typically from a current beyond the limiting current
+    ElectrolyteDepleted { region: Region, time: f64 },
+    // The kinetic law cannot carry the reaction current density at any overpotential, e.g. beyond the limiting
+    // current of Marcus-Hush-Chidsey kinetics
+    LimitingCurrentExceeded { current_density: f64 },
+    // No current satisfies the voltage or power held by the operating mode
+    OperatingPointNotFound { mode: &'static str, target: f64 },
+    // An iterative solver did not converge, e.g. the potentials of the DFN model, at the given time if known
+    NotConverged { solver: &'static str, time: Option<f64> },
+    // A model parameter is outside its physical or numerical range
+    InvalidParameter { parameter: &'static str, reason: &'static str },
+    // An experiment step is not well defined
+    InvalidExperiment { step: usize, reason: &'static str },
+    // A tabulated curve could not be read or is not a function of its first column
+    InvalidTable(String),
+    // Reading input or writing model output failed
+    Io(std::io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::MismatchedLengths { time, current } => write!(
+                f,
+                "Time and current vectors must be the same length, got {time} and {current}"
+            ),
+            Error::EmptyInput => write!(f, "Current vector must not be empty"),
+            Error::UnsortedTime { index } => write!(f, "Time vector must be sorted, unsorted at index {index}"),
+            Error::NonFiniteInput { index } => write!(f, "Time and current vectors must be finite, non-finite at index {index}"),
+            Error::UnstableScheme { component, timestep } => write!(
+                f,
+                "FTCS method not stable for {component} with a timestep of {timestep} s"
+            ),
+            Error::StoichiometryOutOfBounds {
+                region,
+                stoichiometry,
+                time,
+            } => write!(
+                f,
+                "Stoichiometry of {region:?} left [0, 1] at t = {time} s, reached {stoichiometry}"
+            ),
+            Error::ElectrolyteDepleted { region, time } => {
+                write!(f, "Electrolyte in {region:?} depleted at t = {time} s")
+            }
+            Error::LimitingCurrentExceeded { current_density } => write!(
+                f,
+                "Reaction current density of {current_density} A/m^2 exceeds the limiting current of the kinetics"
+            ),
+            Error::OperatingPointNotFound { mode, target } => {
+                write!(f, "No current found that holds the {mode} at {target}")
+            }
+            Error::NotConverged { solver, time: Some(time) } => {
+                write!(f, "The {solver} did not converge at t = {time} s")
+            }
+            Error::NotConverged { solver, time: None } => write!(f, "The {solver} did not converge"),
+            Error::InvalidParameter { parameter, reason } => write!(f, "Invalid {parameter}: {reason}"),
+            Error::InvalidExperiment { step, reason } => write!(f, "Experiment step {step} is invalid: {reason}"),
+            Error::InvalidTable(reason) => write!(f, "Invalid table: {reason}"),
+            Error::Io(error) => write!(f, "I/O error: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Self {
+        Error::Io(error)
+    }
+}
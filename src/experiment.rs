@@ -0,0 +1,315 @@
+use crate::error::{Error, Result};
+use crate::model::{Control, FixedStepping, SPMeModel, TimeStepping};
+use crate::observer::Observer;
+use crate::solution::{OutputSpecification, Sample, Solution};
+
+// Holds the experiment protocol engine, which runs a sequence of operating steps on a model, e.g.
+// "charge at 1C to 4.2 V, hold 4.2 V until C/20, rest 1 h", optionally repeated for cycling.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Mode {
+    Current(f64), // A, positive on charge
+    Voltage(f64), // V
+    Power(f64),   // W, positive on charge
+    Rest,
+    // Current profile where current[k] is applied over (time[k-1], time[k]]
+    DriveCycle { time: Vec<f64>, current: Vec<f64> },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Termination {
+    VoltageAbove(f64),          // V
+    VoltageBelow(f64),          // V
+    CurrentMagnitudeBelow(f64), // A
+    Duration(f64),              // s, time spent in the step
+    Capacity(f64),              // Ah, charge passed in the step, regardless of direction
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    pub mode: Mode,
+    pub timestep: f64, // s, not used by drive cycles, which step along their own time vector
+    pub terminations: Vec<Termination>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Experiment {
+    pub steps: Vec<Step>,
+    pub cycles: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExperimentSolution {
+    pub solution: Solution,
+    pub cycle: Vec<usize>, // cycle index of every sample
+    pub step: Vec<usize>,  // step index within the cycle of every sample
+}
+
+// Progress through a step, used to evaluate the terminations
+#[derive(Debug, Clone, Copy)]
+struct Progress {
+    elapsed: f64, // s
+    charge: f64,  // As
+    voltage: f64, // V
+    current: f64, // A
+}
+
+impl Termination {
+    fn margin(&self, progress: &Progress) -> f64 {
+        // Positive while the step should continue, and zero or negative once the condition is met
+        match self {
+            Termination::VoltageAbove(voltage) => voltage - progress.voltage,
+            Termination::VoltageBelow(voltage) => progress.voltage - voltage,
+            Termination::CurrentMagnitudeBelow(current) => progress.current.abs() - current,
+            Termination::Duration(duration) => duration - progress.elapsed,
+            Termination::Capacity(capacity) => capacity - progress.charge.abs() / 3600.0,
+        }
+    }
+
+    fn is_voltage(&self) -> bool {
+        matches!(self, Termination::VoltageAbove(_) | Termination::VoltageBelow(_))
+    }
+}
+
+impl Step {
+    fn new(mode: Mode) -> Self {
+        Step {
+            mode,
+            timestep: 1.0,
+            terminations: Vec::new(),
+        }
+    }
+
+    pub fn constant_current(current: f64) -> Self {
+        Step::new(Mode::Current(current))
+    }
+
+    pub fn constant_voltage(voltage: f64) -> Self {
+        Step::new(Mode::Voltage(voltage))
+    }
+
+    pub fn constant_power(power: f64) -> Self {
+        Step::new(Mode::Power(power))
+    }
+
+    pub fn rest() -> Self {
+        Step::new(Mode::Rest)
+    }
+
+    pub fn drive_cycle(time: Vec<f64>, current: Vec<f64>) -> Self {
+        Step::new(Mode::DriveCycle { time, current })
+    }
+
+    pub fn until(mut self, termination: Termination) -> Self {
+        self.terminations.push(termination);
+        self
+    }
+
+    pub fn with_timestep(mut self, timestep: f64) -> Self {
+        self.timestep = timestep;
+        self
+    }
+
+    fn validate(&self, index: usize) -> Result<()> {
+        let invalid = |reason: &'static str| Err(Error::InvalidExperiment { step: index, reason });
+        match &self.mode {
+            Mode::DriveCycle { time, current } => {
+                if time.len() != current.len() {
+                    return invalid("drive cycle time and current differ in length");
+                }
+                if time.len() < 2 {
+                    return invalid("drive cycle needs at least two samples");
+                }
+                if !time.windows(2).all(|w| w[0] < w[1]) {
+                    return invalid("drive cycle time must be sorted");
+                }
+            }
+            _ => {
+                if !(self.timestep > 0.0 && self.timestep.is_finite()) {
+                    return invalid("timestep must be positive");
+                }
+                if self.terminations.is_empty() {
+                    return invalid("step never terminates");
+                }
+                // Voltage and current limits may never be reached, so a step needs a limit that is. Charge only
+                // passes at a current that cannot decay to zero.
+                let passes_charge: bool = match self.mode {
+                    Mode::Current(current) => current != 0.0,
+                    Mode::Power(power) => power != 0.0,
+                    _ => false,
+                };
+                let backstop = |termination: &Termination| match termination {
+                    Termination::Duration(_) => true,
+                    Termination::Capacity(_) => passes_charge,
+                    _ => false,
+                };
+                if !self.terminations.iter().any(backstop) {
+                    return invalid("step needs a duration limit, or a capacity limit at constant current or power");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn max_timestep(&self) -> f64 {
+        match &self.mode {
+            Mode::DriveCycle { time, .. } => time.windows(2).map(|w| w[1] - w[0]).fold(0.0, f64::max),
+            _ => self.timestep,
+        }
+    }
+
+    fn substep(&self, k: usize) -> Option<(f64, Control)> {
+        // Length and control of the k-th step, or None at the end of a drive cycle
+        match &self.mode {
+            Mode::Current(current) => Some((self.timestep, Control::Current(*current))),
+            Mode::Voltage(voltage) => Some((self.timestep, Control::Voltage(*voltage))),
+            Mode::Power(power) => Some((self.timestep, Control::Power(*power))),
+            Mode::Rest => Some((self.timestep, Control::Current(0.0))),
+            Mode::DriveCycle { time, current } => {
+                (k + 1 < time.len()).then(|| (time[k + 1] - time[k], Control::Current(current[k + 1])))
+            }
+        }
+    }
+}
+
+impl Experiment {
+    pub fn new(steps: Vec<Step>) -> Self {
+        Experiment { steps, cycles: 1 }
+    }
+
+    pub fn repeat(mut self, cycles: usize) -> Self {
+        self.cycles = cycles;
+        self
+    }
+
+    pub fn run(&self, model: &mut SPMeModel, output: &OutputSpecification) -> Result<ExperimentSolution> {
+        self.run_observed(model, output, &mut ())
+    }
+
+    pub fn run_observed(
+        &self,
+        model: &mut SPMeModel,
+        output: &OutputSpecification,
+        observer: &mut dyn Observer,
+    ) -> Result<ExperimentSolution> {
+        // Steps end on voltage and current events checked once per step, so the steps are of fixed length
+        if let TimeStepping::Adaptive(_) = model.time_stepping {
+            return Err(Error::InvalidParameter {
+                parameter: "time stepping",
+                reason: "experiments take steps of fixed length, use fixed time stepping",
+            });
+        }
+        model.check_parameters()?;
+        for (index, step) in self.steps.iter().enumerate() {
+            step.validate(index)?;
+            if model.time_scheme.is_explicit() {
+                model.check_ftcs_stability(step.max_timestep())?;
+            }
+        }
+
+        // The experiment starts from the present state of the model at rest
+        let mut result: ExperimentSolution = ExperimentSolution {
+            solution: Solution::new(output),
+            ..ExperimentSolution::default()
+        };
+        let initial: Sample = model.sample(0.0, output)?;
+        model.observe(0.0, 0.0, &initial, observer)?;
+        result.push(0, 0, 0.0, 0.0, &initial);
+
+        let mut time: f64 = 0.0;
+        let mut current: f64 = 0.0;
+        for cycle in 0..self.cycles {
+            for (index, step) in self.steps.iter().enumerate() {
+                (time, current) = self.run_step(model, step, cycle, index, time, current, output, observer, &mut result)?;
+            }
+        }
+        observer.finish()?;
+        Ok(result)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn run_step(
+        &self,
+        model: &mut SPMeModel,
+        step: &Step,
+        cycle: usize,
+        index: usize,
+        start_time: f64,
+        start_current: f64,
+        output: &OutputSpecification,
+        observer: &mut dyn Observer,
+        result: &mut ExperimentSolution,
+    ) -> Result<(f64, f64)> {
+        // Runs a single step from the present model state, and returns the time and current at its end
+        let mut progress: Progress = Progress {
+            elapsed: 0.0,
+            charge: 0.0,
+            voltage: model.cell_potential(start_current)?,
+            current: start_current,
+        };
+
+        // A voltage limit that is already reached skips the step, e.g. charging a full cell
+        if step.terminations.iter().any(|t| t.is_voltage() && t.margin(&progress) <= 0.0) {
+            return Ok((start_time, start_current));
+        }
+
+        let mut k: usize = 0;
+        while let Some((dt, control)) = step.substep(k) {
+            let before: SPMeModel = model.clone();
+            let mut applied: f64 = model.step_controlled(dt, control, progress.current)?;
+            let mut next: Progress = Progress {
+                elapsed: progress.elapsed + dt,
+                charge: progress.charge + applied * dt,
+                voltage: model.cell_potential(applied)?,
+                current: applied,
+            };
+
+            // If a termination is met during the step, the step is redone up to where the first one is met,
+            // estimated by linear interpolation of the termination margins
+            let fraction: Option<f64> = step
+                .terminations
+                .iter()
+                .filter(|t| t.margin(&next) <= 0.0)
+                .map(|t| {
+                    let (a, b) = (t.margin(&progress), t.margin(&next));
+                    if a > b { (a / (a - b)).clamp(0.0, 1.0) } else { 1.0 }
+                })
+                .reduce(f64::min);
+            if let Some(fraction) = fraction.filter(|fraction| *fraction < 1.0) {
+                *model = before;
+                if fraction * dt < 1e-6 {
+                    break;
+                }
+                applied = model.step_controlled(fraction * dt, control, progress.current)?;
+                next = Progress {
+                    elapsed: progress.elapsed + fraction * dt,
+                    charge: progress.charge + applied * fraction * dt,
+                    voltage: model.cell_potential(applied)?,
+                    current: applied,
+                };
+            }
+
+            let time: f64 = start_time + next.elapsed;
+            model.check_stoichiometry(time)?;
+            model.electrolyte.check_depletion(time)?;
+            let sample: Sample = model.sample(applied, output)?;
+            model.observe(time, applied, &sample, observer)?;
+            result.push(cycle, index, time, applied, &sample);
+            progress = next;
+
+            if fraction.is_some() {
+                break;
+            }
+            k += 1;
+        }
+        Ok((start_time + progress.elapsed, progress.current))
+    }
+}
+
+impl ExperimentSolution {
+    fn push(&mut self, cycle: usize, step: usize, time: f64, current: f64, sample: &Sample) {
+        self.solution.push(time, current, sample);
+        self.cycle.push(cycle);
+        self.step.push(step);
+    }
+}
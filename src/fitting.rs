@@ -0,0 +1,81 @@
+use crate::error::{Error, Result};
+use crate::math::numerical_methods::levenberg_marquardt;
+use crate::ocv::{OpenCircuitPotential, TanhSeries};
+
+// Holds fitting routines for open circuit potential curves from measured half cell data
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OcvFit {
+    pub series: TanhSeries,
+    pub coefficients: Vec<f64>, // see TanhSeries::coefficients for the layout
+    pub residuals: Vec<f64>,    // V, fitted minus measured potential at every point
+    pub rms_error: f64,         // V
+    pub iterations: usize,      // of levenberg marquardt
+    pub converged: bool,        // false if the iteration limit was reached first
+}
+
+pub fn fit_tanh_series(stoichiometry: &[f64], potential: &[f64], initial: &TanhSeries) -> Result<OcvFit> {
+    // Nonlinear least squares fit of an exponential plus tanh series to measured potentials, starting from an
+    // initial guess that sets the number of tanh terms. The exponential and the linear slope are left out of
+    // the fit if they are zero in the initial guess, so both forms of the LG MJ1 fits are covered.
+    if stoichiometry.len() != potential.len() {
+        return Err(Error::InvalidTable(format!(
+            "{} stoichiometries and {} potentials",
+            stoichiometry.len(),
+            potential.len()
+        )));
+    }
+    let initial_coefficients: Vec<f64> = initial.coefficients();
+    let free: Vec<usize> = (0..initial_coefficients.len())
+        .filter(|&i| match i {
+            0 | 1 => initial.exponential_amplitude != 0.0,
+            3 => initial.slope != 0.0,
+            _ => true,
+        })
+        .collect();
+    if stoichiometry.len() < free.len() {
+        return Err(Error::InvalidTable(format!(
+            "{} points are too few to fit {} coefficients",
+            stoichiometry.len(),
+            free.len()
+        )));
+    }
+
+    let series = |parameters: &[f64]| -> Result<TanhSeries> {
+        let mut coefficients: Vec<f64> = initial_coefficients.clone();
+        for (i, value) in free.iter().zip(parameters) {
+            coefficients[*i] = *value;
+        }
+        TanhSeries::from_coefficients(&coefficients)
+    };
+    let residuals = |series: &TanhSeries| -> Vec<f64> {
+        stoichiometry
+            .iter()
+            .zip(potential)
+            .map(|(x, u)| series.potential(*x) - u)
+            .collect()
+    };
+
+    // The parameters keep the layout of the initial series, so building the series does not fail
+    let start: Vec<f64> = free.iter().map(|i| initial_coefficients[*i]).collect();
+    let (fitted, iterations, converged) = levenberg_marquardt(
+        |parameters: &[f64]| {
+            series(parameters).map_or_else(|_| vec![f64::NAN; stoichiometry.len()], |series| residuals(&series))
+        },
+        &start,
+        500,
+        1e-12,
+    );
+
+    let series: TanhSeries = series(&fitted)?;
+    let residuals: Vec<f64> = residuals(&series);
+    let rms_error: f64 = (residuals.iter().map(|r| r * r).sum::<f64>() / residuals.len() as f64).sqrt();
+    Ok(OcvFit {
+        coefficients: series.coefficients(),
+        series,
+        residuals,
+        rms_error,
+        iterations,
+        converged,
+    })
+}
@@ -0,0 +1,52 @@
+use crate::ocv::OpenCircuitPotential;
+
+use std::sync::Arc;
+
+// One state hysteresis model of Plett (2004), for materials whose open circuit potential depends on the
+// direction of the last (de)lithiation, e.g. silicon blends. The potential moves between a lithiation and a
+// delithiation branch as the state h goes from -1 to 1,
+// $U = \frac{U_{delith} + U_{lith}}{2} + h \frac{U_{delith} - U_{lith}}{2}$
+// and h relaxes towards the branch of the present direction at a rate proportional to the change in stoichiometry,
+// $\frac{dh}{dt} = \gamma \left| \frac{dx}{dt} \right| \left( -\mathrm{sign}\left(\frac{dx}{dt}\right) - h \right)$
+
+#[derive(Debug, Clone)]
+pub struct Hysteresis {
+    pub lithiation: Arc<dyn OpenCircuitPotential>,
+    pub delithiation: Arc<dyn OpenCircuitPotential>,
+    pub decay_rate: f64, // gamma, per unit change in stoichiometry
+    pub state: f64,      // h, -1 on the lithiation branch and 1 on the delithiation branch
+}
+
+impl Hysteresis {
+    pub fn new(lithiation: Arc<dyn OpenCircuitPotential>, delithiation: Arc<dyn OpenCircuitPotential>, decay_rate: f64) -> Self {
+        // Starts halfway between the branches
+        Hysteresis {
+            lithiation,
+            delithiation,
+            decay_rate,
+            state: 0.0,
+        }
+    }
+
+    pub fn potential_at_temperature(&self, stoichiometry: f64, temperature: f64) -> f64 {
+        let lithiation: f64 = self.lithiation.potential_at_temperature(stoichiometry, temperature);
+        let delithiation: f64 = self.delithiation.potential_at_temperature(stoichiometry, temperature);
+        0.5 * (delithiation + lithiation) + 0.5 * self.state * (delithiation - lithiation)
+    }
+
+    pub fn entropic_coefficient(&self, stoichiometry: f64) -> f64 {
+        // dU/dT, weighted between the branches as the potential
+        let lithiation: f64 = self.lithiation.entropic_coefficient(stoichiometry);
+        let delithiation: f64 = self.delithiation.entropic_coefficient(stoichiometry);
+        0.5 * (delithiation + lithiation) + 0.5 * self.state * (delithiation - lithiation)
+    }
+
+    pub fn update(&mut self, stoichiometry_change: f64) {
+        // Exact solution of the state equation over a step with constant direction
+        if stoichiometry_change == 0.0 {
+            return;
+        }
+        let branch: f64 = -stoichiometry_change.signum();
+        self.state = branch + (self.state - branch) * (-self.decay_rate * stoichiometry_change.abs()).exp();
+    }
+}
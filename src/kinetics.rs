@@ -0,0 +1,104 @@
+use crate::error::{Error, Result};
+use crate::math::numerical_methods::safeguarded_newton;
+use crate::math::utils::{arcsinh, erfc};
+use crate::model::{FARADAY, GAS_CONSTANT};
+
+use std::f64::consts::PI;
+
+// Holds the interfacial kinetic laws relating reaction current and surface overpotential.
+// Everything here uses the electrochemical sign convention: anodic (oxidation) current and overpotential are positive.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KineticLaw {
+    // $i = i_0 \left( e^{\alpha_a f \eta} - e^{-\alpha_c f \eta} \right)$, with $f = F/RT$
+    ButlerVolmer {
+        anodic_transfer_coefficient: f64,
+        cathodic_transfer_coefficient: f64,
+    },
+    // Marcus-Hush-Chidsey kinetics with the closed form approximation of Zeng et al. (2014),
+    // https://doi.org/10.1016/j.jelechem.2014.09.038. The current saturates at large overpotentials.
+    MarcusHushChidsey {
+        reorganisation_energy: f64, // eV
+    },
+}
+
+impl Default for KineticLaw {
+    fn default() -> Self {
+        KineticLaw::ButlerVolmer {
+            anodic_transfer_coefficient: 0.5,
+            cathodic_transfer_coefficient: 0.5,
+        }
+    }
+}
+
+impl KineticLaw {
+    pub fn concentration_exponents(&self) -> (f64, f64) {
+        // Exponents of the (electrolyte and vacancy, lithiated site) concentrations in the exchange current density,
+        // $i_0 = k c_e^{\alpha_a} (1 - x)^{\alpha_a} c_s^{\alpha_c}$
+        match self {
+            KineticLaw::ButlerVolmer {
+                anodic_transfer_coefficient,
+                cathodic_transfer_coefficient,
+            } => (*anodic_transfer_coefficient, *cathodic_transfer_coefficient),
+            KineticLaw::MarcusHushChidsey { .. } => (0.5, 0.5),
+        }
+    }
+
+    pub fn current_density(&self, exchange_current_density: f64, overpotential: f64, temperature: f64) -> (f64, f64) {
+        // Reaction current density at a given surface overpotential, and its derivative with respect to the overpotential
+        let f: f64 = FARADAY / (GAS_CONSTANT * temperature);
+        match self {
+            KineticLaw::ButlerVolmer {
+                anodic_transfer_coefficient: alpha_a,
+                cathodic_transfer_coefficient: alpha_c,
+            } => {
+                let anodic: f64 = (alpha_a * f * overpotential).exp();
+                let cathodic: f64 = (-alpha_c * f * overpotential).exp();
+                (
+                    exchange_current_density * (anodic - cathodic),
+                    exchange_current_density * f * (alpha_a * anodic + alpha_c * cathodic),
+                )
+            }
+            KineticLaw::MarcusHushChidsey { reorganisation_energy } => {
+                // Dimensionless overpotential and reorganisation energy (in units of kT/e)
+                let eta: f64 = f * overpotential;
+                let lambda: f64 = f * reorganisation_energy;
+                let sqrt_lambda: f64 = lambda.sqrt();
+                let argument = |eta: f64| (lambda - (1.0 + sqrt_lambda + eta * eta).sqrt()) / (2.0 * sqrt_lambda);
+
+                // The net rate k_ox - k_red is proportional to tanh(eta/2) * erfc(argument(eta)), and is scaled
+                // by the rate at equilibrium so that i_0 keeps its meaning and the slope at eta = 0 matches butler volmer
+                let erfc_equilibrium: f64 = erfc(argument(0.0));
+                let a: f64 = argument(eta);
+                let erfc_eta: f64 = erfc(a);
+                let tanh: f64 = (0.5 * eta).tanh();
+                let d_erfc: f64 = 2.0 / PI.sqrt() * (-a * a).exp() * eta
+                    / (2.0 * sqrt_lambda * (1.0 + sqrt_lambda + eta * eta).sqrt());
+                (
+                    exchange_current_density * 2.0 * tanh * erfc_eta / erfc_equilibrium,
+                    exchange_current_density * f * ((1.0 - tanh * tanh) * erfc_eta + 2.0 * tanh * d_erfc)
+                        / erfc_equilibrium,
+                )
+            }
+        }
+    }
+
+    pub fn overpotential(&self, exchange_current_density: f64, current_density: f64, temperature: f64) -> Result<f64> {
+        // Surface overpotential that drives a given reaction current density. The current is monotonic in the
+        // overpotential, so the inversion is done by safeguarded newton iteration from the symmetric butler volmer
+        // solution. Marcus-Hush-Chidsey kinetics has a limiting current, beyond which the bracket holds no root.
+        let thermal_voltage: f64 = GAS_CONSTANT * temperature / FARADAY;
+        let initial_guess: f64 = 2.0 * thermal_voltage * arcsinh(current_density / (2.0 * exchange_current_density));
+        let bracket: f64 = 200.0 * thermal_voltage; // about 5 V
+        let residual = |eta: f64| -> (f64, f64) {
+            let (current, derivative) = self.current_density(exchange_current_density, eta, temperature);
+            (current - current_density, derivative)
+        };
+        let (low, _) = residual(-bracket);
+        let (high, _) = residual(bracket);
+        if !(low <= 0.0 && high >= 0.0) {
+            return Err(Error::LimitingCurrentExceeded { current_density });
+        }
+        Ok(safeguarded_newton(residual, initial_guess, -bracket, bracket, 1e-12))
+    }
+}
@@ -1,8 +1,47 @@
 // Todo: Build an actual API
+pub mod dfn;
+pub mod error;
+pub mod experiment;
+pub mod fitting;
+pub mod hysteresis;
+pub mod kinetics;
 pub mod math;
 pub mod model;
+pub mod observer;
 pub mod ocv;
+pub mod sei;
+pub mod size_distribution;
+pub mod solution;
+pub mod spm;
+pub mod thermal;
+
+pub use error::{Error, Result};
+pub use observer::Observer;
+pub use solution::{OutputSpecification, Solution};
 
 pub trait Simulate {
-    fn simulate(&mut self, time: &[f64], current: &[f64]) -> Vec<f64>;
+    // Simulates a current profile, returning the terminal voltage together with the quantities selected by
+    // the output specification, or an error for invalid input or if the simulation fails. The observer is
+    // given the model state at every internal timestep.
+    fn solve_observed(
+        &mut self,
+        time: &[f64],
+        current: &[f64],
+        output: &OutputSpecification,
+        observer: &mut dyn Observer,
+    ) -> Result<Solution>;
+
+    fn solve(&mut self, time: &[f64], current: &[f64], output: &OutputSpecification) -> Result<Solution> {
+        self.solve_observed(time, current, output, &mut ())
+    }
+
+    // Terminal voltage only
+    fn try_simulate(&mut self, time: &[f64], current: &[f64]) -> Result<Vec<f64>> {
+        Ok(self.solve(time, current, &OutputSpecification::default())?.voltage)
+    }
+
+    // Panicking version of try_simulate
+    fn simulate(&mut self, time: &[f64], current: &[f64]) -> Vec<f64> {
+        self.try_simulate(time, current).unwrap_or_else(|error| panic!("{error}"))
+    }
 }
@@ -1,5 +1,5 @@
 use pxd::model::SPMeModel;
 
 fn main() {
-    let model = SPMeModel::default();
+    let _model = SPMeModel::default();
 }
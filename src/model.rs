@@ -1,29 +1,33 @@
-use crate::math::numerical_methods::{
-    forward_time_centered_space_linear, forward_time_centered_space_radial, ftcs_stable,
-};
-use crate::math::utils::arcsinh;
-use crate::ocv;
+use crate::math::numerical_methods::{ftcs_stable, TimeScheme};
+use crate::error::{Error, Result};
+use crate::hysteresis::Hysteresis;
+use crate::kinetics::KineticLaw;
+use crate::observer::{Observer, State};
+use crate::ocv::{GraphiteSi, Nmc811, OpenCircuitPotential};
+use crate::sei::Sei;
+use crate::size_distribution::{LognormalDistribution, SizeClass};
+use crate::solution::{ElectrodeSample, OutputSpecification, Sample, Solution};
+use crate::thermal::{ActivationEnergies, HeatGeneration, ThermalModel};
 use crate::Simulate;
 
-use std::f64::consts::PI;
-use std::fs::File;
-use std::io::BufWriter;
-use std::io::Write;
-use std::fs::OpenOptions;
+use std::ops::Range;
+use std::sync::Arc;
 
+// Default mesh sizes, each particle and electrolyte region can be discretised independently at runtime
 pub const PARTICLE_DISCRETISATION: usize = 20;
-const ELECTROLYTE_DISCRETISATION: usize = 20;
-const FARADAY: f64 = 96485.3321233100184; // C/mol (=As/mol), 2019 SI revision definition
-const GAS_CONSTANT: f64 = 8.31446261815324; // J/(mol*K), 2019 SI revision definition
-const STANDARD_TEMPERATURE: f64 = 298.15; // Kelvin
-const CATION_TRANSFERENCE_NUMBER: f64 = 0.2594; // dimensionless
+pub const ELECTROLYTE_DISCRETISATION: usize = 20; // per electrode
+pub const SEPARATOR_DISCRETISATION: usize = 10;
+pub(crate) const FARADAY: f64 = 96485.33212331001; // C/mol (=As/mol), 2019 SI revision definition
+pub(crate) const GAS_CONSTANT: f64 = 8.31446261815324; // J/(mol*K), 2019 SI revision definition
+pub(crate) const STANDARD_TEMPERATURE: f64 = 298.15; // Kelvin
+pub(crate) const CATION_TRANSFERENCE_NUMBER: f64 = 0.2594; // dimensionless
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct Particle {
     pub radius: f64,
     pub dr: f64,
     pub diffusion_coeff: f64,
-    pub concentration: [f64; PARTICLE_DISCRETISATION],
+    pub concentration: Vec<f64>,
     pub concentration_max: f64,
     pub concentration_init: f64,
 }
@@ -36,7 +40,7 @@ impl Particle {
         concentration_init: f64,
     ) -> Self {
         let dr = radius / (PARTICLE_DISCRETISATION as f64);
-        let concentration = [concentration_init; PARTICLE_DISCRETISATION];
+        let concentration = vec![concentration_init; PARTICLE_DISCRETISATION];
 
         Particle {
             radius,
@@ -47,38 +51,490 @@ impl Particle {
             concentration_init,
         }
     }
+
+    pub fn with_discretisation(mut self, nodes: usize) -> Result<Self> {
+        self.set_discretisation(nodes)?;
+        Ok(self)
+    }
+
+    pub fn set_discretisation(&mut self, nodes: usize) -> Result<()> {
+        // Changes the number of radial nodes, the current concentration profile is interpolated onto the new mesh.
+        // The surface flux needs a node at the centre and one at the surface.
+        if nodes < 2 {
+            return Err(Error::InvalidParameter {
+                parameter: "particle discretisation",
+                reason: "at least two radial nodes are needed",
+            });
+        }
+        self.concentration = resample(&self.concentration, nodes);
+        self.dr = self.radius / nodes as f64;
+        Ok(())
+    }
+
+    pub fn surface_concentration(&self) -> f64 {
+        self.concentration[self.concentration.len() - 1]
+    }
+
+    pub fn average_concentration(&self) -> f64 {
+        // Volume average over the nodes at r = i*dr, where each node holds the spherical shell halfway to its
+        // neighbours
+        let n: usize = self.concentration.len();
+        let outer: f64 = (n - 1) as f64 * self.dr;
+        if n == 1 {
+            return self.concentration[0];
+        }
+        self.concentration
+            .iter()
+            .enumerate()
+            .map(|(i, c)| {
+                let r: f64 = i as f64 * self.dr;
+                let lower: f64 = (r - 0.5 * self.dr).max(0.0);
+                let upper: f64 = (r + 0.5 * self.dr).min(outer);
+                c * (upper.powi(3) - lower.powi(3))
+            })
+            .sum::<f64>()
+            / outer.powi(3)
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
+fn resample(profile: &[f64], nodes: usize) -> Vec<f64> {
+    // Linear interpolation of a profile onto a different number of equally spaced nodes spanning the same domain
+    let n: usize = profile.len();
+    if n == 1 || nodes == 1 {
+        return vec![profile.iter().sum::<f64>() / n as f64; nodes];
+    }
+    (0..nodes)
+        .map(|i| {
+            let x: f64 = i as f64 * (n - 1) as f64 / (nodes - 1) as f64;
+            let j: usize = (x.floor() as usize).min(n - 2);
+            let w: f64 = x - j as f64;
+            profile[j] + w * (profile[j + 1] - profile[j])
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone)]
 pub struct Electrolyte {
-    // This is used in place of separator for the time being
-    pub concentration: [f64; ELECTROLYTE_DISCRETISATION],
+    // Spans negative electrode, separator and positive electrode, starting at the negative current collector
+    pub concentration: Vec<f64>,
     pub conductivity: f64,
     pub diffusion_coeff: f64,
-    pub thickness: f64,
+    pub nodes: [usize; 3], // negative electrode, separator, positive electrode
+}
+
+impl Electrolyte {
+    pub fn new(concentration_init: f64, conductivity: f64, diffusion_coeff: f64) -> Self {
+        let nodes: [usize; 3] = [ELECTROLYTE_DISCRETISATION, SEPARATOR_DISCRETISATION, ELECTROLYTE_DISCRETISATION];
+        Electrolyte {
+            concentration: vec![concentration_init; nodes.iter().sum()],
+            conductivity,
+            diffusion_coeff,
+            nodes,
+        }
+    }
+
+    pub fn with_discretisation(mut self, nodes: [usize; 3]) -> Result<Self> {
+        self.set_discretisation(nodes)?;
+        Ok(self)
+    }
+
+    pub fn set_discretisation(&mut self, nodes: [usize; 3]) -> Result<()> {
+        // Changes the number of nodes in each region, the current concentration profile of each region is
+        // interpolated onto the new mesh. Each region needs two nodes to resolve a concentration gradient.
+        self.check_discretisation()?;
+        if nodes.iter().any(|region_nodes| *region_nodes < 2) {
+            return Err(Error::InvalidParameter {
+                parameter: "electrolyte discretisation",
+                reason: "at least two nodes are needed in each region",
+            });
+        }
+        let mut concentration: Vec<f64> = Vec::with_capacity(nodes.iter().sum());
+        for (region, region_nodes) in [Region::NegativeElectrode, Region::Separator, Region::PositiveElectrode]
+            .into_iter()
+            .zip(nodes)
+        {
+            concentration.extend(resample(&self.concentration[self.region_nodes(region)], region_nodes));
+        }
+        self.concentration = concentration;
+        self.nodes = nodes;
+        Ok(())
+    }
+
+    pub fn region_nodes(&self, region: Region) -> Range<usize> {
+        // Indices of the concentration belonging to a region
+        let [n, s, p] = self.nodes;
+        match region {
+            Region::NegativeElectrode => 0..n,
+            Region::Separator => n..n + s,
+            Region::PositiveElectrode => n + s..n + s + p,
+        }
+    }
+
+    pub(crate) fn check_discretisation(&self) -> Result<()> {
+        // The nodes and the concentration are public, and have to describe the same mesh
+        let invalid =
+            |reason: &'static str| Err(Error::InvalidParameter { parameter: "electrolyte discretisation", reason });
+        if self.nodes.iter().any(|region_nodes| *region_nodes < 2) {
+            return invalid("at least two nodes are needed in each region");
+        }
+        if self.nodes.iter().sum::<usize>() != self.concentration.len() {
+            return invalid("the nodes of the regions must add up to the length of the concentration");
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_depletion(&self, time: f64) -> Result<()> {
+        // The kinetics and the concentration overpotential need salt at every node
+        for region in [Region::NegativeElectrode, Region::Separator, Region::PositiveElectrode] {
+            if self.concentration[self.region_nodes(region)].iter().any(|c| c.is_nan() || *c <= 0.0) {
+                return Err(Error::ElectrolyteDepleted { region, time });
+            }
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
+pub struct Separator {
+    pub thickness: f64,
+    pub porosity: f64, // electrolyte volume fraction
+    pub bruggeman_exponent: f64,
+}
+
+#[derive(Debug, Clone)]
 pub struct Electrode {
     pub height: f64,
     pub width: f64,
     pub thickness: f64,
     pub particle: Particle,
+    pub open_circuit_potential: Arc<dyn OpenCircuitPotential>,
+    pub hysteresis: Option<Hysteresis>, // replaces the open circuit potential with hysteresis branches when set
     pub active_material_volume_fraction: f64,
+    pub conductivity: f64, // S/m, electronic conductivity of the solid phase
+    pub porosity: f64, // electrolyte volume fraction
+    pub bruggeman_exponent: f64,
+    pub reaction_rate_constant: f64,
+    pub kinetics: KineticLaw,
+    pub double_layer_capacitance: f64, // F/m^2 of particle surface, zero for quasi-static kinetics
+    pub surface_overpotential: f64,    // V, state of the double layer
+    pub sei: Option<Sei>,              // film growth on the particles, only modelled on the negative electrode
+    // Further active materials next to the primary particle, e.g. silicon in a graphite anode. All materials
+    // sit at a common potential, and the reaction current is shared between them accordingly.
+    pub blend: Vec<ActiveMaterial>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ActiveMaterial {
+    pub particle: Particle,
+    pub open_circuit_potential: Arc<dyn OpenCircuitPotential>,
+    pub hysteresis: Option<Hysteresis>, // replaces the open circuit potential with hysteresis branches when set
+    pub volume_fraction: f64, // of the electrode
+    pub reaction_rate_constant: f64,
+}
+
+impl ActiveMaterial {
+    pub fn open_circuit_potential_at(&self, stoichiometry: f64, temperature: f64) -> f64 {
+        // Open circuit potential on the present hysteresis state, if any
+        match &self.hysteresis {
+            Some(hysteresis) => hysteresis.potential_at_temperature(stoichiometry, temperature),
+            None => self.open_circuit_potential.potential_at_temperature(stoichiometry, temperature),
+        }
+    }
+}
+
+// An active material of an electrode, either the primary particle or a blend component, with its open
+// circuit potential at the present surface stoichiometry
+struct Material<'a> {
+    particle: &'a Particle,
+    volume_fraction: f64,
+    reaction_rate_constant: f64,
+    open_circuit_potential: f64, // V
+}
+
+impl Material<'_> {
+    fn specific_interfacial_surface_area(&self) -> f64 {
+        // The specific interfacial surface area is the surface area per unit volume, and it's use
+        // assumes a uniform distribution of monodisperse spherical particles.
+        // $a = 3 \cdot \frac{\epsilon}{r}$
+        3.0 * self.volume_fraction / self.particle.radius
+    }
+}
+
+fn bruggeman(porosity: f64, bruggeman_exponent: f64) -> f64 {
+    // Bruggeman correction, the fraction of a bulk transport property that remains in a porous medium
+    // due to the reduced cross section and increased path length (tortuosity).
+    // $\epsilon^b$, where b = 1.5 for packed spheres
+    porosity.powf(bruggeman_exponent)
+}
+
+impl Separator {
+    pub fn effective_diffusivity(&self, diffusion_coeff: f64) -> f64 {
+        // Effective diffusivity of the electrolyte in the pores of the separator
+        diffusion_coeff * bruggeman(self.porosity, self.bruggeman_exponent)
+    }
+
+    pub fn effective_conductivity(&self, conductivity: f64) -> f64 {
+        // Effective conductivity of the electrolyte in the pores of the separator
+        conductivity * bruggeman(self.porosity, self.bruggeman_exponent)
+    }
+}
+
+impl Electrode {
+    pub fn effective_diffusivity(&self, diffusion_coeff: f64) -> f64 {
+        // Effective diffusivity of the electrolyte in the pores of the electrode
+        diffusion_coeff * bruggeman(self.porosity, self.bruggeman_exponent)
+    }
+
+    pub fn effective_conductivity(&self, conductivity: f64) -> f64 {
+        // Effective conductivity of the electrolyte in the pores of the electrode
+        conductivity * bruggeman(self.porosity, self.bruggeman_exponent)
+    }
+
+    pub fn effective_solid_conductivity(&self) -> f64 {
+        // Effective electronic conductivity of the solid phase, where the active material is the conducting phase
+        self.conductivity * bruggeman(self.total_volume_fraction(), self.bruggeman_exponent)
+    }
+
+    fn total_volume_fraction(&self) -> f64 {
+        // Volume fraction of all active materials
+        self.volume_fractions().sum()
+    }
+
+    fn volume_fractions(&self) -> impl Iterator<Item = f64> + '_ {
+        // Of the primary particle followed by the particles of the blend
+        std::iter::once(self.active_material_volume_fraction).chain(self.blend.iter().map(|material| material.volume_fraction))
+    }
+
+    fn capacity_weighted(&self, concentration: impl Fn(&Particle) -> f64) -> f64 {
+        // Lithium over the capacity of all active materials, the stoichiometry of the electrode as a whole
+        let (lithium, capacity) = self.particles().zip(self.volume_fractions()).fold(
+            (0.0, 0.0),
+            |(lithium, capacity), (particle, fraction): (&Particle, f64)| {
+                (lithium + fraction * concentration(particle), capacity + fraction * particle.concentration_max)
+            },
+        );
+        lithium / capacity
+    }
+
+    pub fn surface_stoichiometry(&self) -> f64 {
+        self.capacity_weighted(Particle::surface_concentration)
+    }
+
+    pub fn average_stoichiometry(&self) -> f64 {
+        self.capacity_weighted(Particle::average_concentration)
+    }
+
+    pub fn particle_concentration(&self) -> Vec<f64> {
+        // Volume average of the concentration profiles of all active materials over the relative radius, on the
+        // mesh of the primary particle
+        let nodes: usize = self.particle.concentration.len();
+        let total: f64 = self.total_volume_fraction();
+        let mut profile: Vec<f64> = vec![0.0; nodes];
+        for (particle, fraction) in self.particles().zip(self.volume_fractions()) {
+            for (average, concentration) in profile.iter_mut().zip(resample(&particle.concentration, nodes)) {
+                *average += fraction / total * concentration;
+            }
+        }
+        profile
+    }
+
+    pub(crate) fn check_volume_fractions(&self) -> Result<()> {
+        // Active materials and electrolyte share the electrode volume, the rest is binder and conductive additive
+        let invalid =
+            |reason: &'static str| Err(Error::InvalidParameter { parameter: "electrode volume fractions", reason });
+        if !(self.porosity > 0.0 && self.porosity < 1.0) {
+            return invalid("porosity must be in (0, 1)");
+        }
+        if self.total_volume_fraction() <= 0.0 {
+            return invalid("active material volume fraction must be positive");
+        }
+        if self.total_volume_fraction() + self.porosity > 1.0 + 1e-12 {
+            return invalid("active material volume fraction and porosity add up to more than one");
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_quasi_static(&self, parameter: &'static str) -> Result<()> {
+        // For the reduced models, which step only the primary particle with quasi-static kinetics. The electrodes of those models are public, so they check again before every run.
+        self.check_volume_fractions()?;
+        let unsupported = |reason: &'static str| Err(Error::InvalidParameter { parameter, reason });
+        if !self.blend.is_empty() {
+            return unsupported("blends and size distributions are not supported");
+        }
+        if self.double_layer_capacitance > 0.0 {
+            return unsupported("double layers are not supported");
+        }
+        if self.sei.is_some() {
+            return unsupported("SEI growth is not supported");
+        }
+        Ok(())
+    }
+
+    pub fn open_circuit_potential_at(&self, stoichiometry: f64, temperature: f64) -> f64 {
+        // Open circuit potential on the present hysteresis state, if any
+        match &self.hysteresis {
+            Some(hysteresis) => hysteresis.potential_at_temperature(stoichiometry, temperature),
+            None => self.open_circuit_potential.potential_at_temperature(stoichiometry, temperature),
+        }
+    }
+
+    pub fn entropic_coefficient(&self, stoichiometry: f64) -> f64 {
+        // dU/dT on the present hysteresis state, if any
+        match &self.hysteresis {
+            Some(hysteresis) => hysteresis.entropic_coefficient(stoichiometry),
+            None => self.open_circuit_potential.entropic_coefficient(stoichiometry),
+        }
+    }
+
+    pub fn particles(&self) -> impl Iterator<Item = &Particle> {
+        // The primary particle followed by the particles of the blend
+        std::iter::once(&self.particle).chain(self.blend.iter().map(|material| &material.particle))
+    }
+
+    pub fn particles_mut(&mut self) -> impl Iterator<Item = &mut Particle> {
+        std::iter::once(&mut self.particle).chain(self.blend.iter_mut().map(|material| &mut material.particle))
+    }
+
+    pub fn set_size_distribution(&mut self, distribution: &LognormalDistribution, classes: usize) -> Result<()> {
+        // Replaces the primary particle with particle classes of the same material, which keep its mesh size,
+        // concentration profile, hysteresis and total volume fraction. The first class becomes the primary particle
+        // and the others are placed first in the blend. The classes share the current at a common potential, which
+        // is equal surface overpotential while they are at the same surface stoichiometry.
+        // An electrode that already has a blend, including one from an earlier distribution, would be subdivided
+        // again, so only a single material is accepted.
+        if !self.blend.is_empty() {
+            return Err(Error::InvalidParameter {
+                parameter: "size distribution",
+                reason: "the electrode already has a blend or size distribution",
+            });
+        }
+        let volume_fraction: f64 = self.active_material_volume_fraction;
+        let particle = |class: &SizeClass| Particle {
+            radius: class.radius,
+            dr: class.radius / self.particle.concentration.len() as f64,
+            ..self.particle.clone()
+        };
+        let size_classes: Vec<SizeClass> = distribution.classes(classes)?;
+        let materials: Vec<ActiveMaterial> = size_classes[1..]
+            .iter()
+            .map(|class| ActiveMaterial {
+                particle: particle(class),
+                open_circuit_potential: self.open_circuit_potential.clone(),
+                hysteresis: self.hysteresis.clone(),
+                volume_fraction: class.volume_share * volume_fraction,
+                reaction_rate_constant: self.reaction_rate_constant,
+            })
+            .collect();
+        self.particle = particle(&size_classes[0]);
+        self.active_material_volume_fraction = size_classes[0].volume_share * volume_fraction;
+        self.blend.splice(0..0, materials);
+        Ok(())
+    }
+
+    fn materials(&self, temperature: f64) -> Vec<Material<'_>> {
+        // Only differences between the open circuit potentials are used, so they are left out without a blend
+        let stoichiometry = |particle: &Particle| particle.surface_concentration() / particle.concentration_max;
+        let primary: Material = Material {
+            particle: &self.particle,
+            volume_fraction: self.active_material_volume_fraction,
+            reaction_rate_constant: self.reaction_rate_constant,
+            open_circuit_potential: if self.blend.is_empty() {
+                0.0
+            } else {
+                self.open_circuit_potential_at(stoichiometry(&self.particle), temperature)
+            },
+        };
+        std::iter::once(primary)
+            .chain(self.blend.iter().map(|material| Material {
+                particle: &material.particle,
+                volume_fraction: material.volume_fraction,
+                reaction_rate_constant: material.reaction_rate_constant,
+                open_circuit_potential: material
+                    .open_circuit_potential_at(stoichiometry(&material.particle), temperature),
+            }))
+            .collect()
+    }
+
+    pub(crate) fn stoichiometries(&self) -> Vec<f64> {
+        // Average stoichiometry of every particle
+        self.particles().map(|particle| particle.average_concentration() / particle.concentration_max).collect()
+    }
+
+    pub(crate) fn update_hysteresis(&mut self, previous_stoichiometries: &[f64]) {
+        // Every material follows the direction of its own (de)lithiation
+        let changes: Vec<f64> =
+            self.stoichiometries().iter().zip(previous_stoichiometries).map(|(x, previous)| x - previous).collect();
+        let hystereses = std::iter::once(&mut self.hysteresis)
+            .chain(self.blend.iter_mut().map(|material| &mut material.hysteresis));
+        for (hysteresis, change) in hystereses.zip(changes) {
+            if let Some(hysteresis) = hysteresis {
+                hysteresis.update(change);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Region {
+    NegativeElectrode,
+    Separator,
+    PositiveElectrode,
 }
 
 #[derive(Debug, Clone)]
 pub struct SPMeModel {
     pub negative_electrode: Electrode,
     pub positive_electrode: Electrode,
+    pub separator: Separator,
     pub electrolyte: Electrolyte,
-    pub concentration: Vec<[f64; ELECTROLYTE_DISCRETISATION]>,
+    pub time_scheme: TimeScheme,
+    pub time_stepping: TimeStepping,
+    pub contact_resistance: f64, // Ohm, lumped contact and tab resistance
+    pub thermal: Option<ThermalModel>, // isothermal at the standard temperature when None
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeStepping {
+    // One step per interval of the input time vector
+    Fixed,
+    // Internal steps chosen from a local error estimate, interpolated onto the input time vector
+    Adaptive(AdaptiveStepping),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Control {
+    Current(f64), // A, positive on charge
+    Voltage(f64), // V, the current is solved for every step
+    Power(f64),   // W, positive on charge, the current is solved for every step
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdaptiveStepping {
+    pub relative_tolerance: f64,
+    pub absolute_tolerance: f64, // mol/m^3
+    pub voltage_tolerance: f64,  // V, for the double layer overpotentials
+    pub initial_timestep: f64,   // seconds
+    pub min_timestep: f64,       // seconds
+    pub max_timestep: f64,       // seconds
+}
+
+impl Default for AdaptiveStepping {
+    fn default() -> Self {
+        AdaptiveStepping {
+            relative_tolerance: 1e-4,
+            absolute_tolerance: 1e-2, // mol/m^3
+            voltage_tolerance: 1e-4,  // V
+            initial_timestep: 1e-3,   // seconds
+            min_timestep: 1e-6,       // seconds
+            max_timestep: 60.0,       // seconds
+        }
+    }
 }
 
 impl Default for SPMeModel {
     // Default parameters for an LG MJ1 18650 cylindrical cell
     fn default() -> Self {
-        let model = SPMeModel {
+        SPMeModel {
             negative_electrode: Electrode {
                 height: 0.059,      // meters
                 width: 1.22,        // meters
@@ -89,7 +545,18 @@ impl Default for SPMeModel {
                     34684.0, // mol/m^3
                     1000.0,
                 ),
+                open_circuit_potential: Arc::new(GraphiteSi),
+                hysteresis: None,
                 active_material_volume_fraction: 0.694,
+                conductivity: 215.0, // S/m
+                porosity: 0.25,
+                bruggeman_exponent: 1.5,
+                reaction_rate_constant: 1e-3,
+                kinetics: KineticLaw::default(),
+                double_layer_capacitance: 0.2, // F/m^2
+                surface_overpotential: 0.0,
+                sei: None,
+                blend: Vec::new(),
             },
             positive_electrode: Electrode {
                 height: 0.059,      // meters
@@ -101,229 +568,1049 @@ impl Default for SPMeModel {
                     50060.0, // mol/m^3
                     49000.0, // mol/m^3
                 ),
+                open_circuit_potential: Arc::new(Nmc811),
+                hysteresis: None,
                 active_material_volume_fraction: 0.754,
+                conductivity: 0.18, // S/m
+                porosity: 0.246,    // fills the electrode with the active material, neglecting binder
+                bruggeman_exponent: 1.5,
+                reaction_rate_constant: 1e-3,
+                kinetics: KineticLaw::default(),
+                double_layer_capacitance: 0.2, // F/m^2
+                surface_overpotential: 0.0,
+                sei: None,
+                blend: Vec::new(),
             },
-            electrolyte: Electrolyte {
-                concentration: [1000.0; ELECTROLYTE_DISCRETISATION],
-                conductivity: 0.8,      // S/m
-                diffusion_coeff: 1.7e-10, // m^2/s avg of Nyman et al. (2008) (fluctuates between 2.2e-10-1.3e-10 between 800-1200mol/m^3)
-                thickness: 12e-6,      // meters
+            separator: Separator {
+                thickness: 12e-6, // meters
+                porosity: 0.47,
+                bruggeman_exponent: 1.5,
             },
-            concentration: vec![[1000.0; ELECTROLYTE_DISCRETISATION]; 1],
-        };
-        model
+            electrolyte: Electrolyte::new(
+                1000.0,   // mol/m^3
+                0.8,      // S/m
+                1.7e-10,  // m^2/s avg of Nyman et al. (2008) (fluctuates between 2.2e-10-1.3e-10 between 800-1200mol/m^3)
+            ),
+            time_scheme: TimeScheme::ForwardTimeCenteredSpace,
+            time_stepping: TimeStepping::Fixed,
+            contact_resistance: 0.01, // Ohm
+            thermal: None,
+        }
     }
 }
 
 impl SPMeModel {
-    fn assert_ftcs_stability(&self, dt: f64) {
-        // Check stability of numerical method in particles and electrolyte
-        assert!(
-            ftcs_stable(
-                dt,
-                self.negative_electrode.particle.radius / PARTICLE_DISCRETISATION as f64,
-                self.negative_electrode.particle.diffusion_coeff
-            ),
-            "FTCS method not stable for negative particle"
-        );
-        assert!(
-            ftcs_stable(
-                dt,
-                self.positive_electrode.particle.radius / PARTICLE_DISCRETISATION as f64,
-                self.positive_electrode.particle.diffusion_coeff
-            ),
-            "FTCS method not stable for positive particle"
-        );
-        assert!(
-            ftcs_stable(
-                dt,
-                self.electrolyte.thickness / ELECTROLYTE_DISCRETISATION as f64,
-                self.electrolyte.diffusion_coeff
-            ),
-            "FTCS method not stable for electrolyte"
-        );
+    pub(crate) fn check_parameters(&self) -> Result<()> {
+        // Parameters the simulation can't run with, or would ignore
+        self.electrolyte.check_discretisation()?;
+        self.negative_electrode.check_volume_fractions()?;
+        self.positive_electrode.check_volume_fractions()?;
+        if self.positive_electrode.sei.is_some() {
+            return Err(Error::InvalidParameter {
+                parameter: "SEI",
+                reason: "SEI growth is only modelled on the negative electrode",
+            });
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_quasi_static(&self, parameter: &'static str) -> Result<()> {
+        // For the reduced models, which run at the standard temperature and can't take over the parts of the
+        // SPMe beyond that
+        self.check_parameters()?;
+        self.negative_electrode.check_quasi_static(parameter)?;
+        self.positive_electrode.check_quasi_static(parameter)?;
+        if self.thermal.is_some() {
+            return Err(Error::InvalidParameter { parameter, reason: "the thermal model is not supported" });
+        }
+        Ok(())
+    }
+
+    pub(crate) fn quasi_static() -> SPMeModel {
+        // The default model without double layers, the starting point of the reduced models
+        let mut model: SPMeModel = SPMeModel::default();
+        model.negative_electrode.double_layer_capacitance = 0.0;
+        model.positive_electrode.double_layer_capacitance = 0.0;
+        model
+    }
+
+    pub(crate) fn check_stoichiometry(&self, time: f64) -> Result<()> {
+        for region in [Region::NegativeElectrode, Region::PositiveElectrode] {
+            check_surface_stoichiometry(region, self.electrode(region).particles(), time)?;
+        }
+        Ok(())
+    }
+
+
+    fn electrode(&self, region: Region) -> &Electrode {
+        match region {
+            Region::NegativeElectrode => &self.negative_electrode,
+            Region::PositiveElectrode => &self.positive_electrode,
+            Region::Separator => unreachable!("The separator is not an electrode"),
+        }
+    }
+
+    pub fn electrolyte_average_concentration(&self, region: Region) -> f64 {
+        // Average electrolyte concentration in a region, mol/m^3. The nodes of a region are equally wide.
+        let concentration: &[f64] = &self.electrolyte.concentration[self.electrolyte.region_nodes(region)];
+        concentration.iter().sum::<f64>() / concentration.len() as f64
+    }
+
+    fn exchange_current_density(&self, region: Region, particle: &Particle, reaction_rate_constant: f64) -> f64 {
+        // The electrolyte concentration is averaged over the electrode, consistent with the uniform reaction
+        // current through the electrode thickness.
+        let c_e: f64 = self.electrolyte_average_concentration(region); // electrolyte concentration mol/m^3
+        let rate: f64 = reaction_rate_constant * self.reaction_rate_factor(region);
+        exchange_current_density(&self.electrode(region).kinetics, rate, particle, c_e)
+    }
+
+    pub fn temperature(&self) -> f64 {
+        // Cell temperature in K, the standard temperature without a thermal model
+        self.thermal.map_or(STANDARD_TEMPERATURE, |thermal| thermal.temperature)
+    }
+
+    fn arrhenius(&self, activation_energy: impl Fn(&ActivationEnergies) -> f64) -> f64 {
+        // Factor on a parameter at the cell temperature, one without a thermal model
+        self.thermal
+            .map_or(1.0, |thermal| thermal.arrhenius(activation_energy(&thermal.activation_energies)))
+    }
+
+    fn particle_diffusivity_factor(&self, region: Region) -> f64 {
+        match region {
+            Region::NegativeElectrode => self.arrhenius(|energies| energies.negative_diffusion),
+            _ => self.arrhenius(|energies| energies.positive_diffusion),
+        }
+    }
+
+    fn reaction_rate_factor(&self, region: Region) -> f64 {
+        match region {
+            Region::NegativeElectrode => self.arrhenius(|energies| energies.negative_reaction),
+            _ => self.arrhenius(|energies| energies.positive_reaction),
+        }
+    }
+
+    pub(crate) fn polarity(region: Region) -> f64 {
+        // Converts between the model convention, where positive current is charge and the overpotentials are
+        // subtracted from the cell potential, and the electrochemical convention of the kinetic laws, where
+        // anodic current and overpotential are positive. Charge is cathodic at the negative electrode and
+        // anodic at the positive electrode.
+        match region {
+            Region::NegativeElectrode => -1.0,
+            _ => 1.0,
+        }
+    }
+
+    fn butler_volmer_overpotential(&self, current_density: f64, region: Region) -> Result<f64> {
+        // Quasi-static reaction overpotential, eta, for the kinetic law of the electrode
+        let electrode: &Electrode = self.electrode(region);
+        let p: f64 = SPMeModel::polarity(region);
+        let reaction_area: f64 = SPMeModel::specific_interfacial_surface_area(electrode) * electrode.thickness;
+        let eta: f64 = electrode.kinetics.overpotential(
+            self.exchange_current_density(region, &electrode.particle, electrode.reaction_rate_constant),
+            p * current_density / reaction_area,
+            self.temperature(),
+        )?;
+        let mut eta: f64 = - p * eta; // negative sign since we define positive current as charge.
+        if electrode.blend.is_empty() {
+            return Ok(eta);
+        }
+
+        // The materials of a blend share the current at a common potential. Starting from the overpotential as
+        // if all of the surface were the primary material, newton iteration on the total faradaic current gives
+        // the overpotential of the primary material. The step is limited, as the materials may be far from
+        // equilibrium with each other.
+        for _ in 0..100 {
+            let (faradaic, d_faradaic) = self.faradaic_current_density(eta, region);
+            let delta: f64 = ((faradaic - current_density) / d_faradaic).clamp(-0.05, 0.05);
+            eta -= delta;
+            if delta.abs() < 1e-12 {
+                return Ok(eta);
+            }
+        }
+        Err(Error::NotConverged { solver: "blend current split", time: None })
+    }
+
+    fn faradaic_current_density(&self, overpotential: f64, region: Region) -> (f64, f64) {
+        // Inverse of butler_volmer_overpotential, the current density (per electrode area) carried by the
+        // reaction at a given surface overpotential of the primary material. Also returns the derivative with
+        // respect to the overpotential.
+        self.material_current_densities(overpotential, region)
+            .iter()
+            .fold((0.0, 0.0), |(total, d_total), (current_density, derivative)| {
+                (total + current_density, d_total + derivative)
+            })
     }
 
+    fn material_current_densities(&self, overpotential: f64, region: Region) -> Vec<(f64, f64)> {
+        // Current density carried by each material, and its derivative, at a given surface overpotential of the
+        // primary material. At a common potential, the overpotential of a material differs from that of the
+        // primary material by the difference in their open circuit potentials.
+        let electrode: &Electrode = self.electrode(region);
+        let temperature: f64 = self.temperature();
+        let materials: Vec<Material> = electrode.materials(temperature);
+        let p: f64 = SPMeModel::polarity(region);
+        materials
+            .iter()
+            .map(|material| {
+                let eta: f64 = overpotential
+                    + p * (material.open_circuit_potential - materials[0].open_circuit_potential);
+                let reaction_area: f64 = material.specific_interfacial_surface_area() * electrode.thickness;
+                let (current_density, derivative) = electrode.kinetics.current_density(
+                    self.exchange_current_density(region, material.particle, material.reaction_rate_constant),
+                    - p * eta,
+                    temperature,
+                );
+                (p * reaction_area * current_density, - reaction_area * derivative)
+            })
+            .collect()
+    }
 
-    fn butler_volmer_overpotential(&self, current_density: f64, electrode: &Electrode) -> f64 {
-        // Butler volmer overpotential, eta
-        // TODO: make B-V function parameters tunable
-        let alpha: f64 = 0.5; // charge transfer coefficient
-        let reaction_rate_constant: f64 = 1e-3; // reaction rate constant
-        let c_e: f64 = 1000.0; // electrolyte concentration mol/m^3, TODO: change this to real value
-        let exchange_current_density: f64 = 
-            reaction_rate_constant 
-            * c_e.powf(alpha) 
-            * electrode.particle.concentration[PARTICLE_DISCRETISATION - 1].powf(alpha)
-            * (1.0 - ( electrode.particle.concentration[PARTICLE_DISCRETISATION - 1] / electrode.particle.concentration_max ) ).powf(alpha);
+    fn step_surface_overpotential(&self, current_density: f64, region: Region, dt: f64) -> Result<f64> {
+        // The double layer is a capacitor in parallel with the reaction, so the applied current splits into a
+        // faradaic and a capacitive part, and the surface overpotential lags behind the applied current:
+        // $C_{dl} a L \frac{d\eta}{dt} = -(i - i_f(\eta))$
+        // This is stiff (time constant of a few ms), so it is stepped by backward euler and solved by newton
+        // iteration. The residual is monotonic in eta, so newton converges from the quasi-static solution.
+        let electrode: &Electrode = self.electrode(region);
+        let capacitance: f64 = electrode.double_layer_capacitance
+            * SPMeModel::specific_interfacial_surface_area(electrode)
+            * electrode.thickness; // F/m^2 of electrode area
+        let eta_old: f64 = electrode.surface_overpotential;
+        // A single sample takes a step of zero length, which leaves no time to charge the double layer
+        if dt <= 0.0 {
+            return Ok(eta_old);
+        }
 
-        // Symmetric butler volmer, only valid for alpha = 0.5
-        let butler_volmer: f64 = ( 2.0 * GAS_CONSTANT * STANDARD_TEMPERATURE / FARADAY )
-            * arcsinh(
-                current_density / ( 2.0* exchange_current_density * self.specific_interfacial_surface_area(electrode) * electrode.thickness )
-            );
-        - butler_volmer // negative sign since we define positive current as charge.
+        let mut eta: f64 = self.butler_volmer_overpotential(current_density, region)?;
+        for _ in 0..50 {
+            let (faradaic, d_faradaic) = self.faradaic_current_density(eta, region);
+            let residual: f64 = capacitance * (eta - eta_old) / dt + current_density - faradaic;
+            let d_residual: f64 = capacitance / dt - d_faradaic;
+            let delta: f64 = residual / d_residual;
+            eta -= delta;
+            if delta.abs() < 1e-12 {
+                break;
+            }
+        }
+        Ok(eta)
+    }
+
+    fn surface_overpotential(&self, current_density: f64, region: Region) -> Result<f64> {
+        // The double layer state if it is modelled, else the quasi-static butler volmer overpotential
+        let electrode: &Electrode = self.electrode(region);
+        if electrode.double_layer_capacitance > 0.0 {
+            Ok(electrode.surface_overpotential)
+        } else {
+            self.butler_volmer_overpotential(current_density, region)
+        }
     }
 
     fn electrolyte_concentration_overpotential(&self) -> f64 {
         // The electrolyte concentration overpotential is the voltage induced by the concentration gradient in the electrolyte.
+        // The end concentrations are those at the negative and positive current collectors.
         let electrolyte_concentration_n: f64 = self.electrolyte.concentration[0];
-        let electrolyte_concentration_p: f64 = self.electrolyte.concentration[ELECTROLYTE_DISCRETISATION - 1];
-        let eta_c: f64 = 
-            2.0 // Accounts for potential drop at both sides
+        let electrolyte_concentration_p: f64 = self.electrolyte.concentration[self.electrolyte.concentration.len() - 1];
+        2.0 // Accounts for potential drop at both sides
             * ( 1.0 - CATION_TRANSFERENCE_NUMBER ) // Describes how much of the current is carried by cations (Li+)
-            * (GAS_CONSTANT * STANDARD_TEMPERATURE / FARADAY) // Nernst potential part 1
-            * (electrolyte_concentration_p - electrolyte_concentration_n); // Nernst potential part 2
-        eta_c
+            * (GAS_CONSTANT * self.temperature() / FARADAY) // Nernst potential part 1
+            * (electrolyte_concentration_p / electrolyte_concentration_n).ln() // Nernst potential part 2
+    }
+
+    pub(crate) fn cell_potential(&self, current: f64) -> Result<f64> {
+        Ok(self.sample(current, &OutputSpecification::default())?.voltage())
     }
 
-    fn cell_potential(&self, current: f64) -> f64 {
+    pub(crate) fn sample(&self, current: f64, output: &OutputSpecification) -> Result<Sample> {
+        // Every contribution to the cell potential at the present state, see Solution for how they add up
         let cell_area: f64 = self.negative_electrode.height * self.negative_electrode.width;
         let current_density: f64 = current / cell_area; // A/m^2
 
-        // Open circuit voltages, U(c)
-        ocv::open_circuit_voltage_nmc811(&self.positive_electrode.particle)
-            - ocv::open_circuit_voltage_graphite_si(&self.negative_electrode.particle)
-            // Reaction/charge transfer overpotential, eta_r
-            - self.butler_volmer_overpotential(current_density, &self.negative_electrode)
-            - self.butler_volmer_overpotential(current_density, &self.positive_electrode)
+        let electrode_sample = |region: Region| -> Result<ElectrodeSample> {
+            // The potentials are those of the primary material, which all materials of a blend share, while the
+            // stoichiometries and profiles cover all materials
+            let electrode: &Electrode = self.electrode(region);
+            let particle: &Particle = &electrode.particle;
+            let primary_stoichiometry: f64 = particle.surface_concentration() / particle.concentration_max;
+            Ok(ElectrodeSample {
+                // Open circuit voltages, U(c)
+                open_circuit_potential: electrode.open_circuit_potential_at(primary_stoichiometry, self.temperature()),
+                // Reaction/charge transfer overpotential, eta_r
+                surface_overpotential: self.surface_overpotential(current_density, region)?,
+                surface_stoichiometry: electrode.surface_stoichiometry(),
+                average_stoichiometry: electrode.average_stoichiometry(),
+                particle_concentration: if output.particle_concentration {
+                    electrode.particle_concentration()
+                } else {
+                    Vec::new()
+                },
+            })
+        };
+
+        Ok(Sample {
+            temperature: self.temperature(),
+            negative_electrode: electrode_sample(Region::NegativeElectrode)?,
+            positive_electrode: electrode_sample(Region::PositiveElectrode)?,
             // Electrolyte concentration overpotential, eta_c
-            + self.electrolyte_concentration_overpotential()
-            // TODO: Ohmic overpotential in solid
-            // TODO: Ohmic overpotential in electrolyte
+            electrolyte_concentration_overpotential: self.electrolyte_concentration_overpotential(),
+            // Ohmic overpotential in solid
+            solid_ohmic_overpotential: SPMeModel::solid_ohmic_overpotential(
+                &self.negative_electrode,
+                &self.positive_electrode,
+                current_density,
+            ),
+            // Ohmic overpotential in electrolyte
+            electrolyte_ohmic_overpotential: self.electrolyte_ohmic_overpotential(current_density),
+            // Contact and tab resistance
+            contact_overpotential: current * self.contact_resistance,
+            // Ionic resistance of the SEI film
+            film_overpotential: self.film_overpotential(current_density),
+            electrolyte_concentration: if output.electrolyte_concentration {
+                self.electrolyte.concentration.clone()
+            } else {
+                Vec::new()
+            },
+        })
     }
 
-    fn specific_interfacial_surface_area(&self, electrode: &Electrode) -> f64 {
-        // The specific interfacial surface area is the surface area per unit volume, and it's use
-        // assumes a uniform distribution of monodisperse spherical particles.
-        // $a = 3 \cdot \frac{\epsilon}{r}$
-        let a: f64 = 3.0 * electrode.active_material_volume_fraction / electrode.particle.radius;
-        a
+    fn film_overpotential(&self, current_density: f64) -> f64 {
+        // The current through the particle surface of the negative electrode crosses the SEI film,
+        // $\frac{i}{a L} L_{sei} \rho_{sei}$
+        let electrode: &Electrode = &self.negative_electrode;
+        match &electrode.sei {
+            Some(sei) => {
+                let reaction_area: f64 = SPMeModel::specific_interfacial_surface_area(electrode) * electrode.thickness;
+                current_density / reaction_area * sei.film_resistance()
+            }
+            None => 0.0,
+        }
     }
 
-    fn particle_surface_flux(&self, current: f64, electrode: &Electrode) -> f64 {
+    fn sei_current(&self, current: f64) -> Result<f64> {
+        // Side reaction current of the SEI in A, positive as it consumes lithium. The electrode potential versus
+        // Li/Li+ is the open circuit potential of the primary material plus its surface overpotential, which all
+        // materials of a blend share.
+        let electrode: &Electrode = &self.negative_electrode;
+        let Some(sei) = &electrode.sei else {
+            return Ok(0.0);
+        };
+        let cell_area: f64 = electrode.height * electrode.width;
+        let stoichiometry: f64 = electrode.particle.surface_concentration() / electrode.particle.concentration_max;
+        let potential: f64 = electrode.open_circuit_potential_at(stoichiometry, self.temperature())
+            + self.surface_overpotential(current / cell_area, Region::NegativeElectrode)?;
+        let surface_area: f64 = SPMeModel::specific_interfacial_surface_area(electrode) * electrode.thickness * cell_area;
+        Ok(sei.current_density(potential, self.temperature()) * surface_area)
+    }
+
+    pub(crate) fn solid_ohmic_overpotential(
+        negative_electrode: &Electrode,
+        positive_electrode: &Electrode,
+        current_density: f64,
+    ) -> f64 {
+        // With uniform reaction through the electrode, the electronic current falls linearly from the
+        // current collector to the separator, which averages to a third of the electrode thickness.
+        // $i \left( \frac{L_n}{3 \sigma_{n,eff}} + \frac{L_p}{3 \sigma_{p,eff}} \right)$
+        current_density
+            * ( negative_electrode.thickness / (3.0 * negative_electrode.effective_solid_conductivity())
+                + positive_electrode.thickness / (3.0 * positive_electrode.effective_solid_conductivity()) )
+    }
+
+    fn electrolyte_ohmic_overpotential(&self, current_density: f64) -> f64 {
+        // The ionic current rises linearly through each electrode and carries the full current through the
+        // separator, which gives the SPMe electrolyte ohmic term with effective conductivities.
+        // $i \left( \frac{L_n}{3 \kappa_{n,eff}} + \frac{L_s}{\kappa_{s,eff}} + \frac{L_p}{3 \kappa_{p,eff}} \right)$
+        let kappa: f64 = self.electrolyte.conductivity * self.arrhenius(|energies| energies.electrolyte_conductivity);
+        current_density
+            * ( self.negative_electrode.thickness / (3.0 * self.negative_electrode.effective_conductivity(kappa))
+                + self.separator.thickness / self.separator.effective_conductivity(kappa)
+                + self.positive_electrode.thickness / (3.0 * self.positive_electrode.effective_conductivity(kappa)) )
+    }
+
+    pub(crate) fn specific_interfacial_surface_area(electrode: &Electrode) -> f64 {
+        // Surface area per unit volume of all active materials of the electrode, see Material. Shared with the SPM
+        // model, whose electrodes only have the primary particle.
+        3.0 * electrode.active_material_volume_fraction / electrode.particle.radius
+            + electrode
+                .blend
+                .iter()
+                .map(|material| 3.0 * material.volume_fraction / material.particle.radius)
+                .sum::<f64>()
+    }
+
+    fn particle_surface_fluxes(&self, current: f64, region: Region) -> Result<Vec<f64>> {
         // The calculation of flux at the particle surface is the current density (cell current divided by electrode area) 
         // divided by Faraday's constant, $F$, (conversion of current to moles), the 
         // specific interfacial surface area, $a$, and the thickness of the electrode, $L$.
+        // The faradaic current of a blend is first shared between its materials. The flux is positive out of the
+        // particles, so charge gives a negative flux in the negative electrode, whose particles it lithiates.
+        let electrode: &Electrode = self.electrode(region);
         let current_density: f64 = current / (electrode.height * electrode.width); // A/m^2
-        let a: f64 = self.specific_interfacial_surface_area(electrode);
-        let flux: f64 = current_density / ( FARADAY * a * electrode.thickness ); // mol/(s*m^2)
-        flux
+        if electrode.blend.is_empty() {
+            let a: f64 = SPMeModel::specific_interfacial_surface_area(electrode);
+            return Ok(vec![SPMeModel::polarity(region) * current_density / ( FARADAY * a * electrode.thickness )]);
+        }
+        let eta: f64 = self.butler_volmer_overpotential(current_density, region)?;
+        let current_densities: Vec<f64> = self
+            .material_current_densities(eta, region)
+            .iter()
+            .map(|(current_density, _)| *current_density)
+            .collect();
+        Ok(electrode
+            .materials(self.temperature())
+            .iter()
+            .zip(current_densities)
+            .map(|(material, current_density)| {
+                let a: f64 = material.specific_interfacial_surface_area();
+                let flux: f64 = current_density / ( FARADAY * a * electrode.thickness ); // mol/(s*m^2)
+                SPMeModel::polarity(region) * flux
+            })
+            .collect())
     }
 
-    fn electrolyte_boundary_flux(&self, current: f64) -> f64 {
-        // The flux at the electrolyte boundary is the current density (cell current divided by electrode area) 
-        // divided by Faraday's constant, $F$, (conversion of current to moles), the 
-        // specific interfacial surface area, $a$, and the thickness of the electrolyte, $L$.
-        let electrode_area: f64 = self.negative_electrode.height * self.negative_electrode.width;
-        let current_density: f64 = current / electrode_area; // A/m^2
-        let flux: f64 = current_density / FARADAY; // mol/(s*m^2)
-        flux
-    }
-
-    fn save_to_file(&self, vec: &Vec<[f64;20]>, filename: &str) -> std::io::Result<()> {
-        let file = OpenOptions::new()
-            .create(true) // Create the file if it doesn't exist
-            .append(true) // Open the file in append mode
-            .open(filename)?;
-        let mut writer = BufWriter::new(file);
-    
-        // Iterate over each line (inner Vec<f64>)
-        for line_vec in vec {
-            let line = line_vec
-                .iter()
-                .map(|value| value.to_string()) // Convert each value to a string
-                .collect::<Vec<String>>()
-                .join(","); // Join values with commas
-    
-            // Write the entire line to the file
-            writeln!(writer, "{}", line)?;
-        }
-    
-        Ok(())
+    fn electrolyte_mesh(&self) -> (Vec<f64>, Vec<f64>, Vec<f64>) {
+        let (dx, porosity, diffusivity) =
+            electrolyte_mesh(&self.negative_electrode, &self.separator, &self.positive_electrode, &self.electrolyte);
+        let factor: f64 = self.arrhenius(|energies| energies.electrolyte_diffusion);
+        (dx, porosity, diffusivity.iter().map(|d| d * factor).collect())
     }
 
-    fn save_model_state(&self) -> std::io::Result<()> {
-        self.save_to_file(
-            &self.concentration.to_vec(),
-            "electrolyte_concentration.csv",
-        )?;
-        Ok(())
+    fn electrolyte_source(&self, current_n: f64, current_p: f64) -> Vec<f64> {
+        // Volumetric source of lithium ions in the electrolyte, mol/(m^3*s). The reaction current is
+        // assumed uniform through each electrode, so the reaction source in an electrode is the
+        // current density divided by Faraday's constant and the electrode thickness. Lithium is
+        // consumed in the negative electrode and released in the positive electrode during charge.
+        // Migration carries the fraction t+ of the ionic current as cations, which reduces the net
+        // source to (1 - t+) of the reaction source. The separator has no reaction.
+        // current_n and current_p are the faradaic currents of each electrode.
+        let region_source = |current: f64, electrode: &Electrode| -> f64 {
+            let current_density: f64 = current / (electrode.height * electrode.width); // A/m^2
+            let reaction: f64 = current_density / (FARADAY * electrode.thickness);
+            let migration: f64 = -CATION_TRANSFERENCE_NUMBER * reaction;
+            reaction + migration
+        };
+        let source_n: f64 = -region_source(current_n, &self.negative_electrode);
+        let source_p: f64 = region_source(current_p, &self.positive_electrode);
+
+        let mut source: Vec<f64> = vec![0.0; self.electrolyte.concentration.len()];
+        source[self.electrolyte.region_nodes(Region::NegativeElectrode)].fill(source_n);
+        source[self.electrolyte.region_nodes(Region::PositiveElectrode)].fill(source_p);
+        source
     }
 
-}
+    pub(crate) fn step_controlled(&mut self, dt: f64, control: Control, current_guess: f64) -> Result<f64> {
+        // Steps the model forward under current, voltage or power control, and returns the applied current
+        let current: f64 = match control {
+            Control::Current(current) => current,
+            Control::Voltage(voltage) => {
+                self.solve_current(dt, current_guess, "voltage", voltage, |v: f64, _: f64| v - voltage)?
+            }
+            Control::Power(power) => {
+                // V*I = P has a second, unphysical, root at large current of the opposite sign,
+                // so start from the current that gives the power at the present voltage
+                let guess: f64 = if current_guess * power > 0.0 {
+                    current_guess
+                } else {
+                    power / self.cell_potential(0.0)?
+                };
+                self.solve_current(dt, guess, "power", power, |v: f64, i: f64| v * i - power)?
+            }
+        };
+        self.step(dt, current)?;
+        Ok(current)
+    }
 
-impl Simulate for SPMeModel {
-    fn simulate(&mut self, time: &[f64], current: &[f64]) -> Vec<f64> {
-        // Check that the time and current vectors are the same length
-        assert_eq!(
-            time.len(),
-            current.len(),
-            "Time and current vectors must be the same length"
-        );
-        // Check that the time vector is sorted
-        assert!(
-            time.windows(2).all(|w| w[0] < w[1]),
-            "Time vector must be sorted"
-        );
-        // Check that the current vector is not empty
-        assert!(!current.is_empty(), "Current vector must not be empty");
-        // Check that the time vector has a constant timestep
-        let dt: f64 = time[1] - time[0];
-        assert!(
-            time.windows(2).all(|w| (w[1] - w[0] - dt).abs() < 1e-9),
-            "Time vector must have a constant timestep"
+    fn solve_current<F>(&self, dt: f64, current_guess: f64, mode: &'static str, target: f64, residual: F) -> Result<f64>
+    where
+        F: Fn(f64, f64) -> f64,
+    {
+        // Finds the constant current over the next step that makes residual(cell potential, current) vanish at
+        // the end of the step. Newton iteration on the current, where the derivative is taken by finite
+        // differences of trial steps, and steps into unphysical states are halved until the residual improves.
+        let evaluate = |current: f64| -> f64 {
+            let mut trial: SPMeModel = self.clone();
+            match trial.step(dt, current).and_then(|_| trial.cell_potential(current)) {
+                Ok(voltage) => residual(voltage, current),
+                Err(_) => f64::NAN,
+            }
+        };
+
+        let mut current: f64 = current_guess;
+        let mut r: f64 = evaluate(current);
+        for _ in 0..50 {
+            if r.abs() < 1e-9 {
+                return Ok(current);
+            }
+            let h: f64 = 1e-6 * current.abs().max(1.0);
+            let derivative: f64 = (evaluate(current + h) - r) / h;
+            let mut delta: f64 = -r / derivative;
+            let mut r_next: f64 = evaluate(current + delta);
+            for _ in 0..30 {
+                if r_next.is_finite() && r_next.abs() < r.abs() {
+                    break;
+                }
+                delta *= 0.5;
+                r_next = evaluate(current + delta);
+            }
+            if !r_next.is_finite() {
+                break;
+            }
+            current += delta;
+            r = r_next;
+            if delta.abs() < 1e-12 * current.abs().max(1.0) {
+                return Ok(current);
+            }
+        }
+        Err(Error::OperatingPointNotFound { mode, target })
+    }
+
+    pub(crate) fn step(&mut self, dt: f64, current: f64) -> Result<()> {
+        // Steps the double layers, electrolyte and particle concentrations, the temperature and the SEI forward by
+        // one timestep at constant current. Heat and SEI growth follow the state at the start of the step.
+        let heat: Option<HeatGeneration> = self.thermal.map(|_| self.heat_generation(current)).transpose()?;
+        let sei_current: f64 = self.sei_current(current)?;
+
+        // Split the current into faradaic and double layer charging current
+        let (eta_n, current_n) = self.step_double_layer(current, Region::NegativeElectrode, dt)?;
+        let (eta_p, current_p) = self.step_double_layer(current, Region::PositiveElectrode, dt)?;
+        self.negative_electrode.surface_overpotential = eta_n;
+        self.positive_electrode.surface_overpotential = eta_p;
+
+        // Step the electrolyte concentration in time
+        let (dx, porosity, diffusivity) = self.electrolyte_mesh();
+        let source: Vec<f64> = self.electrolyte_source(current_n, current_p);
+        self.time_scheme.step_finite_volume(
+            &mut self.electrolyte.concentration,
+            &dx,
+            &porosity,
+            &diffusivity,
+            &source,
+            dt,
         );
 
-        self.assert_ftcs_stability(dt);
-
-        // Set up cell potential over time
-        let mut cell_potential: Vec<f64> = vec![0.0; time.len()];
-        let electrolyte_dx: f64 = self.electrolyte.thickness / ELECTROLYTE_DISCRETISATION as f64;
-
-        for i in 0..time.len() {
-            // Step the electrolyte concentration in time
-            let flux_e: f64 = self.electrolyte_boundary_flux(current[i]);
-            forward_time_centered_space_linear(
-                &mut self.electrolyte.concentration,
-                electrolyte_dx,
-                dt,
-                self.electrolyte.diffusion_coeff,
-                flux_e/1000.0,
-            );
-
-            // Step the particles' concentration in time
-            let flux_n: f64 = -self.particle_surface_flux(current[i], &self.negative_electrode);
-            forward_time_centered_space_radial(
-                &mut self.negative_electrode.particle.concentration,
-                self.negative_electrode.particle.dr,
-                dt,
-                self.negative_electrode.particle.diffusion_coeff,
-                self.negative_electrode.particle.radius,
-                flux_n, // flux
-            );
-            let flux_p: f64 = self.particle_surface_flux(current[i], &self.positive_electrode);
-            forward_time_centered_space_radial(
-                &mut self.positive_electrode.particle.concentration,
-                self.positive_electrode.particle.dr,
-                dt,
-                self.positive_electrode.particle.diffusion_coeff,
-                self.positive_electrode.particle.radius,
-                flux_p, // flux
-            );
-
-            // Calculate cell potential
-            cell_potential[i] = self.cell_potential(current[i]);
-            
-            // TODO: Find a better way to save timeseries model state.
-            if std::env::var("WRITE_MODEL_OUTPUT").is_ok() {
-                self.concentration.push(self.electrolyte.concentration);
+        // Step the particles' concentration in time
+        let stoichiometries_n: Vec<f64> = self.negative_electrode.stoichiometries();
+        let stoichiometries_p: Vec<f64> = self.positive_electrode.stoichiometries();
+        // The SEI takes its lithium from the negative particles, while the electrolyte sees the total current
+        let flux_n: Vec<f64> = self.particle_surface_fluxes(current_n - sei_current, Region::NegativeElectrode)?;
+        let flux_p: Vec<f64> = self.particle_surface_fluxes(current_p, Region::PositiveElectrode)?;
+        let factor_n: f64 = self.particle_diffusivity_factor(Region::NegativeElectrode);
+        let factor_p: f64 = self.particle_diffusivity_factor(Region::PositiveElectrode);
+        let time_scheme: TimeScheme = self.time_scheme;
+        for (electrode, fluxes, factor) in [
+            (&mut self.negative_electrode, flux_n, factor_n),
+            (&mut self.positive_electrode, flux_p, factor_p),
+        ] {
+            for (particle, flux) in electrode.particles_mut().zip(fluxes) {
+                time_scheme.step_radial(
+                    &mut particle.concentration,
+                    particle.dr,
+                    dt,
+                    particle.diffusion_coeff * factor,
+                    particle.radius,
+                    flux, // flux
+                );
             }
         }
-        if std::env::var("WRITE_MODEL_OUTPUT").is_ok() {
-            self.save_model_state().unwrap();
+
+        // The hysteresis states follow the direction of (de)lithiation
+        self.negative_electrode.update_hysteresis(&stoichiometries_n);
+        self.positive_electrode.update_hysteresis(&stoichiometries_p);
+
+        if let (Some(thermal), Some(heat)) = (&mut self.thermal, heat) {
+            thermal.step(&heat, dt);
+        }
+
+        let electrode: &Electrode = &self.negative_electrode;
+        let surface_area: f64 =
+            SPMeModel::specific_interfacial_surface_area(electrode) * electrode.thickness * electrode.height * electrode.width;
+        if let Some(sei) = &mut self.negative_electrode.sei {
+            sei.step(sei_current / surface_area, surface_area, dt);
         }
-        cell_potential
-        
+        Ok(())
     }
+
+    pub fn heat_generation(&self, current: f64) -> Result<HeatGeneration> {
+        // Heat generated in the cell at the present state, with positive current as charge:
+        // irreversible heat of the reaction and concentration overpotentials, $I (V - U - \eta_{ohm})$,
+        // reversible heat of the electrode reactions, $I T \left( \frac{dU_p}{dT} - \frac{dU_n}{dT} \right)$,
+        // and ohmic heat in solid, electrolyte, contacts and SEI film, $I \eta_{ohm}$
+        let sample: Sample = self.sample(current, &OutputSpecification::default())?;
+        let ohmic: f64 = sample.solid_ohmic_overpotential
+            + sample.electrolyte_ohmic_overpotential
+            + sample.contact_overpotential
+            + sample.film_overpotential;
+        let open_circuit_voltage: f64 =
+            sample.positive_electrode.open_circuit_potential - sample.negative_electrode.open_circuit_potential;
+        // Of the primary material, whose open circuit potential the sample reports
+        let primary_stoichiometry = |electrode: &Electrode| -> f64 {
+            electrode.particle.surface_concentration() / electrode.particle.concentration_max
+        };
+        let entropic_coefficient: f64 = self
+            .positive_electrode
+            .entropic_coefficient(primary_stoichiometry(&self.positive_electrode))
+            - self.negative_electrode.entropic_coefficient(primary_stoichiometry(&self.negative_electrode));
+        Ok(HeatGeneration {
+            irreversible: current * (sample.voltage() - open_circuit_voltage - ohmic),
+            reversible: current * sample.temperature * entropic_coefficient,
+            ohmic: current * ohmic,
+        })
+    }
+
+    fn step_double_layer(&self, current: f64, region: Region, dt: f64) -> Result<(f64, f64)> {
+        // Returns the surface overpotential of an electrode after one timestep, and the faradaic part of the current.
+        // Without a double layer all of the current is faradaic.
+        let electrode: &Electrode = self.electrode(region);
+        if electrode.double_layer_capacitance <= 0.0 {
+            return Ok((electrode.surface_overpotential, current));
+        }
+        let area: f64 = electrode.height * electrode.width;
+        let eta: f64 = self.step_surface_overpotential(current / area, region, dt)?;
+        let (faradaic, _) = self.faradaic_current_density(eta, region);
+        Ok((eta, faradaic * area))
+    }
+
+    pub fn solve_controlled(
+        &mut self,
+        time: &[f64],
+        control: &[Control],
+        output: &OutputSpecification,
+    ) -> Result<Solution> {
+        self.solve_controlled_observed(time, control, output, &mut ())
+    }
+
+    pub fn solve_controlled_observed(
+        &mut self,
+        time: &[f64],
+        control: &[Control],
+        output: &OutputSpecification,
+        observer: &mut dyn Observer,
+    ) -> Result<Solution> {
+        // Simulates a profile of operating modes, where control[k] is held over (time[k-1], time[k]]. Voltage
+        // and power control solve for the current at every step, so there is one step per interval and adaptive
+        // time stepping is not supported.
+        let targets: Vec<f64> = control
+            .iter()
+            .map(|control| match *control {
+                Control::Current(value) | Control::Voltage(value) | Control::Power(value) => value,
+            })
+            .collect();
+        check_input(time, &targets)?;
+        if let TimeStepping::Adaptive(_) = self.time_stepping {
+            return Err(Error::InvalidParameter {
+                parameter: "time stepping",
+                reason: "controlled simulations take one step per interval, use fixed time stepping",
+            });
+        }
+        self.check_parameters()?;
+        let solution: Solution = solve_fixed(self, time, control, output, observer)?;
+        observer.finish()?;
+        Ok(solution)
+    }
+
+    fn simulate_adaptive(
+        &mut self,
+        time: &[f64],
+        current: &[f64],
+        settings: &AdaptiveStepping,
+        output: &OutputSpecification,
+        observer: &mut dyn Observer,
+    ) -> Result<Solution> {
+        // The current is held constant over each interval (time[k-1], time[k]] at the value current[k].
+        // Intervals with equal current are merged into segments, and each segment is integrated with
+        // internal steps chosen from a step doubling error estimate. The model output at the
+        // accepted steps is linearly interpolated back onto the input time vector.
+        let mut solution: Solution = Solution::new(output);
+        if time.len() == 1 {
+            let initial: Sample = self.sample(current[0], output)?;
+            self.observe(time[0], current[0], &initial, observer)?;
+            solution.push(time[0], current[0], &initial);
+            return Ok(solution);
+        }
+
+        // As with fixed time stepping, the first sample follows a step at current[0] over the spacing to the
+        // second sample, so the integration starts that far before time[0]
+        let time: Vec<f64> = std::iter::once(2.0 * time[0] - time[1]).chain(time.iter().copied()).collect();
+        let current: Vec<f64> = std::iter::once(current[0]).chain(current.iter().copied()).collect();
+        let n: usize = time.len();
+
+        // FTCS is still subject to its stability limit
+        let max_timestep: f64 = if self.time_scheme.is_explicit() {
+            settings.max_timestep.min(self.ftcs_timestep_limit())
+        } else {
+            settings.max_timestep
+        };
+        let initial_timestep: f64 = settings.initial_timestep.clamp(settings.min_timestep, max_timestep);
+
+        let mut segment_start: usize = 0;
+        while segment_start < n - 1 {
+            let segment_current: f64 = current[segment_start + 1];
+            let mut segment_end: usize = segment_start + 1;
+            while segment_end < n - 1 && current[segment_end + 1] == segment_current {
+                segment_end += 1;
+            }
+
+            // Restart with a small step at every change of current to resolve the double layer transient
+            let mut dt: f64 = initial_timestep;
+            let mut t: f64 = time[segment_start];
+            let mut previous: Sample = self.sample(segment_current, output)?;
+            let mut k: usize = segment_start + 1; // next output sample
+            while k <= segment_end {
+                let remaining: f64 = time[segment_end] - t;
+                let step_dt: f64 = dt.min(remaining);
+
+                let (accepted, dt_next) = self.try_adaptive_step(step_dt, segment_current, settings)?;
+                // Shortening the last step of a segment should not shrink the following steps, while a rejected
+                // step is always retried with a shorter one
+                dt = if accepted && step_dt < dt { dt_next.max(dt) } else { dt_next }.min(max_timestep);
+                if !accepted {
+                    continue;
+                }
+
+                let t_next: f64 = if step_dt == remaining { time[segment_end] } else { t + step_dt };
+                self.check_stoichiometry(t_next)?;
+                self.electrolyte.check_depletion(t_next)?;
+                let next: Sample = self.sample(segment_current, output)?;
+                while k <= segment_end && time[k] <= t_next {
+                    let w: f64 = (time[k] - t) / (t_next - t);
+                    let sample: Sample = previous.interpolate(&next, w);
+                    if k == 1 {
+                        self.observe(time[k], current[k], &sample, observer)?;
+                    }
+                    solution.push(time[k], current[k], &sample);
+                    k += 1;
+                }
+                // The observer sees the first sample and the accepted steps after it
+                if t_next > time[1] {
+                    self.observe(t_next, segment_current, &next, observer)?;
+                }
+                t = t_next;
+                previous = next;
+            }
+            segment_start = segment_end;
+        }
+
+        Ok(solution)
+    }
+
+    fn try_adaptive_step(&mut self, dt: f64, current: f64, settings: &AdaptiveStepping) -> Result<(bool, f64)> {
+        // Step doubling: one full step is compared to two half steps, and the difference is an
+        // estimate of the local error. The two half steps are kept if the error is within tolerance.
+        // Returns whether the step was accepted, and the suggested next timestep.
+        let mut full: SPMeModel = self.clone();
+        full.step(dt, current)?;
+        let mut half: SPMeModel = self.clone();
+        half.step(0.5 * dt, current)?;
+        half.step(0.5 * dt, current)?;
+
+        let order: i32 = self.time_scheme.order();
+        let error: f64 = half.state_error(&full, settings) / (2.0_f64.powi(order) - 1.0);
+
+        let factor: f64 = if error > 0.0 {
+            (0.9 * error.powf(-1.0 / (order as f64 + 1.0))).clamp(0.2, 5.0)
+        } else {
+            5.0
+        };
+        let dt_next: f64 = (dt * factor).clamp(settings.min_timestep, settings.max_timestep);
+
+        if error <= 1.0 || dt <= settings.min_timestep {
+            *self = half;
+            Ok((true, dt_next))
+        } else {
+            Ok((false, dt_next))
+        }
+    }
+
+    fn state_error(&self, other: &SPMeModel, settings: &AdaptiveStepping) -> f64 {
+        // Largest concentration or overpotential difference between two model states, scaled by the tolerances
+        let scaled = |a: &[f64], b: &[f64]| -> f64 {
+            a.iter()
+                .zip(b)
+                .map(|(x, y)| {
+                    (x - y).abs()
+                        / (settings.absolute_tolerance + settings.relative_tolerance * x.abs().max(y.abs()))
+                })
+                .fold(0.0, f64::max)
+        };
+        let particles = self.negative_electrode.particles().chain(self.positive_electrode.particles());
+        let other_particles = other.negative_electrode.particles().chain(other.positive_electrode.particles());
+        particles
+            .zip(other_particles)
+            .map(|(a, b)| scaled(&a.concentration, &b.concentration))
+            .fold(scaled(&self.electrolyte.concentration, &other.electrolyte.concentration), f64::max)
+            .max(
+                (self.negative_electrode.surface_overpotential - other.negative_electrode.surface_overpotential).abs()
+                    / settings.voltage_tolerance,
+            )
+            .max(
+                (self.positive_electrode.surface_overpotential - other.positive_electrode.surface_overpotential).abs()
+                    / settings.voltage_tolerance,
+            )
+    }
+
+    fn ftcs_timestep_limit(&self) -> f64 {
+        // Largest timestep for which FTCS is stable in particles and electrolyte
+        let (dx, porosity, diffusivity) = self.electrolyte_mesh();
+        let limit = |dx: f64, alpha: f64| dx * dx / (2.0 * alpha);
+        [Region::NegativeElectrode, Region::PositiveElectrode]
+            .into_iter()
+            .flat_map(|region| {
+                let factor: f64 = self.particle_diffusivity_factor(region);
+                self.electrode(region)
+                    .particles()
+                    .map(move |particle| limit(particle.dr, particle.diffusion_coeff * factor))
+            })
+            .fold(f64::INFINITY, f64::min)
+            .min(
+                (0..dx.len())
+                    .map(|i| limit(dx[i], diffusivity[i] / porosity[i]))
+                    .fold(f64::INFINITY, f64::min),
+            )
+    }
+}
+
+impl FixedStepping for SPMeModel {
+    type Input = Control;
+
+    fn time_scheme(&self) -> TimeScheme {
+        self.time_scheme
+    }
+
+    fn check_ftcs_stability(&self, dt: f64) -> Result<()> {
+        // Check stability of numerical method in particles and electrolyte
+        for region in [Region::NegativeElectrode, Region::PositiveElectrode] {
+            let factor: f64 = self.particle_diffusivity_factor(region);
+            check_particle_stability(region, self.electrode(region).particles(), factor, dt)?;
+        }
+        let (dx, porosity, diffusivity) = self.electrolyte_mesh();
+        check_electrolyte_stability(&dx, &porosity, &diffusivity, dt)
+    }
+
+    fn step_sample(
+        &mut self,
+        dt: f64,
+        control: Control,
+        previous_current: f64,
+        time: f64,
+        output: &OutputSpecification,
+    ) -> Result<(f64, Sample)> {
+        let current: f64 = self.step_controlled(dt, control, previous_current)?;
+        self.check_stoichiometry(time)?;
+        self.electrolyte.check_depletion(time)?;
+        Ok((current, self.sample(current, output)?))
+    }
+
+    fn observe(&self, time: f64, current: f64, sample: &Sample, observer: &mut dyn Observer) -> Result<()> {
+        observer.observe(&State {
+            time,
+            current,
+            voltage: sample.voltage(),
+            negative_particle_concentration: &self.negative_electrode.particle_concentration(),
+            positive_particle_concentration: &self.positive_electrode.particle_concentration(),
+            electrolyte_concentration: &self.electrolyte.concentration,
+        })
+    }
+}
+
+impl Simulate for SPMeModel {
+    fn solve_observed(
+        &mut self,
+        time: &[f64],
+        current: &[f64],
+        output: &OutputSpecification,
+        observer: &mut dyn Observer,
+    ) -> Result<Solution> {
+        check_input(time, current)?;
+        self.check_parameters()?;
+        let solution: Solution = match self.time_stepping {
+            TimeStepping::Fixed => {
+                let control: Vec<Control> = current.iter().map(|current| Control::Current(*current)).collect();
+                solve_fixed(self, time, &control, output, observer)?
+            }
+            TimeStepping::Adaptive(settings) => self.simulate_adaptive(time, current, &settings, output, observer)?,
+        };
+        observer.finish()?;
+        Ok(solution)
+    }
+}
+
+pub(crate) fn check_input(time: &[f64], input: &[f64]) -> Result<()> {
+    // Check that the time and current vectors are the same length
+    if time.len() != input.len() {
+        return Err(Error::MismatchedLengths {
+            time: time.len(),
+            current: input.len(),
+        });
+    }
+    // Check that the current vector is not empty
+    if input.is_empty() {
+        return Err(Error::EmptyInput);
+    }
+    // Check that every sample is finite, as a NaN would pass the sorting check and poison the solution
+    if let Some(index) = time.iter().zip(input).position(|(t, i)| !t.is_finite() || !i.is_finite()) {
+        return Err(Error::NonFiniteInput { index });
+    }
+    // Check that the time vector is sorted
+    if let Some(index) = time.windows(2).position(|w| w[0] >= w[1]) {
+        return Err(Error::UnsortedTime { index: index + 1 });
+    }
+    Ok(())
+}
+
+// A model that takes one step per sample. Implicit schemes are unconditionally stable, so only the largest step
+// is checked for FTCS.
+pub(crate) trait FixedStepping {
+    // Applied at every step, e.g. the current or a control mode
+    type Input: Copy;
+
+    fn time_scheme(&self) -> TimeScheme;
+
+    fn check_ftcs_stability(&self, dt: f64) -> Result<()>;
+
+    // Steps the state forward with the input, checks it at the end of the step and samples it. Returns the current
+    // over the step, where the previous current is a starting point for control modes that solve for it.
+    fn step_sample(
+        &mut self,
+        dt: f64,
+        input: Self::Input,
+        previous_current: f64,
+        time: f64,
+        output: &OutputSpecification,
+    ) -> Result<(f64, Sample)>;
+
+    fn observe(&self, time: f64, current: f64, sample: &Sample, observer: &mut dyn Observer) -> Result<()>;
+}
+
+pub(crate) fn solve_fixed<M: FixedStepping>(
+    model: &mut M,
+    time: &[f64],
+    input: &[M::Input],
+    output: &OutputSpecification,
+    observer: &mut dyn Observer,
+) -> Result<Solution> {
+    // One step per sample, where the step length is the spacing to the previous sample.
+    // The first sample uses the spacing to the second sample.
+    let n: usize = time.len();
+    let first_dt: f64 = if n > 1 { time[1] - time[0] } else { 0.0 };
+    if model.time_scheme().is_explicit() {
+        let max_dt: f64 = time.windows(2).map(|w| w[1] - w[0]).fold(first_dt, f64::max);
+        model.check_ftcs_stability(max_dt)?;
+    }
+
+    let mut solution: Solution = Solution::new(output);
+    let mut current: f64 = 0.0;
+    for i in 0..n {
+        let dt: f64 = if i == 0 { first_dt } else { time[i] - time[i - 1] };
+        let sample: Sample;
+        (current, sample) = model.step_sample(dt, input[i], current, time[i], output)?;
+        model.observe(time[i], current, &sample, observer)?;
+        solution.push(time[i], current, &sample);
+    }
+    Ok(solution)
+}
+
+pub(crate) fn check_particle_stability<'a>(
+    region: Region,
+    particles: impl IntoIterator<Item = &'a Particle>,
+    diffusivity_factor: f64,
+    dt: f64,
+) -> Result<()> {
+    // FTCS stability in the particles of an electrode, whose diffusivity is scaled by the factor
+    let component: &'static str = match region {
+        Region::NegativeElectrode => "negative particle",
+        _ => "positive particle",
+    };
+    if !particles
+        .into_iter()
+        .all(|particle| ftcs_stable(dt, particle.dr, particle.diffusion_coeff * diffusivity_factor))
+    {
+        return Err(Error::UnstableScheme { component, timestep: dt });
+    }
+    Ok(())
+}
+
+pub(crate) fn check_electrolyte_stability(dx: &[f64], porosity: &[f64], diffusivity: &[f64], dt: f64) -> Result<()> {
+    // The porosity slows down diffusion in the electrolyte, so the stable timestep grows with it
+    if !(0..dx.len()).all(|i| ftcs_stable(dt, dx[i], diffusivity[i] / porosity[i])) {
+        return Err(Error::UnstableScheme {
+            component: "electrolyte",
+            timestep: dt,
+        });
+    }
+    Ok(())
+}
+
+pub(crate) fn check_surface_stoichiometry<'a>(
+    region: Region,
+    particles: impl IntoIterator<Item = &'a Particle>,
+    time: f64,
+) -> Result<()> {
+    // The open circuit voltage and kinetics are only defined for surface stoichiometries in [0, 1]
+    for particle in particles {
+        let stoichiometry: f64 = particle.surface_concentration() / particle.concentration_max;
+        if !(0.0..=1.0).contains(&stoichiometry) {
+            return Err(Error::StoichiometryOutOfBounds {
+                region,
+                stoichiometry,
+                time,
+            });
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn electrolyte_mesh(
+    negative_electrode: &Electrode,
+    separator: &Separator,
+    positive_electrode: &Electrode,
+    electrolyte: &Electrolyte,
+) -> (Vec<f64>, Vec<f64>, Vec<f64>) {
+    // Cell widths, porosities and effective diffusivities of the electrolyte, from the negative to the
+    // positive current collector
+    let d: f64 = electrolyte.diffusion_coeff;
+    let [nodes_n, nodes_s, nodes_p] = electrolyte.nodes;
+    let regions: [(f64, f64, f64, usize); 3] = [
+        (
+            negative_electrode.thickness,
+            negative_electrode.porosity,
+            negative_electrode.effective_diffusivity(d),
+            nodes_n,
+        ),
+        (
+            separator.thickness,
+            separator.porosity,
+            separator.effective_diffusivity(d),
+            nodes_s,
+        ),
+        (
+            positive_electrode.thickness,
+            positive_electrode.porosity,
+            positive_electrode.effective_diffusivity(d),
+            nodes_p,
+        ),
+    ];
+    let total_nodes: usize = electrolyte.concentration.len();
+    let mut dx: Vec<f64> = Vec::with_capacity(total_nodes);
+    let mut porosity: Vec<f64> = Vec::with_capacity(total_nodes);
+    let mut diffusivity: Vec<f64> = Vec::with_capacity(total_nodes);
+    for (thickness, region_porosity, region_diffusivity, nodes) in regions {
+        dx.extend(std::iter::repeat_n(thickness / nodes as f64, nodes));
+        porosity.extend(std::iter::repeat_n(region_porosity, nodes));
+        diffusivity.extend(std::iter::repeat_n(region_diffusivity, nodes));
+    }
+    (dx, porosity, diffusivity)
+}
+
+pub(crate) fn exchange_current_density(
+    kinetics: &KineticLaw,
+    reaction_rate_constant: f64,
+    particle: &Particle,
+    electrolyte_concentration: f64,
+) -> f64 {
+    // Exchange current density at the surface of a particle, A/m^2. A depleted electrolyte carries no exchange
+    // current, rather than a NaN from the power of a negative concentration.
+    let (alpha_a, alpha_c) = kinetics.concentration_exponents();
+    let c_s: f64 = particle.surface_concentration();
+    reaction_rate_constant
+        * electrolyte_concentration.max(0.0).powf(alpha_a)
+        * c_s.powf(alpha_c)
+        * (1.0 - ( c_s / particle.concentration_max ) ).powf(alpha_a)
 }
@@ -0,0 +1,193 @@
+use crate::error::Result;
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::num::NonZeroUsize;
+use std::path::Path;
+
+// Observers receive the model state while a simulation runs, e.g. to write timeseries to file. They are
+// called at every internal timestep, so wrap them in EveryNSeconds or Decimate to thin out the output.
+
+#[derive(Debug, Clone, Copy)]
+pub struct State<'a> {
+    pub time: f64,    // s
+    pub current: f64, // A, positive on charge
+    pub voltage: f64, // V
+    pub negative_particle_concentration: &'a [f64], // mol/m^3, centre to surface
+    pub positive_particle_concentration: &'a [f64], // mol/m^3, centre to surface
+    pub electrolyte_concentration: &'a [f64], // mol/m^3, negative to positive current collector
+}
+
+pub trait Observer {
+    fn observe(&mut self, state: &State) -> Result<()>;
+
+    // Called once after the last state of a simulation
+    fn finish(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+// Observes nothing, used when no observer is given
+impl Observer for () {
+    fn observe(&mut self, _state: &State) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<O: Observer + ?Sized> Observer for &mut O {
+    fn observe(&mut self, state: &State) -> Result<()> {
+        (**self).observe(state)
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        (**self).finish()
+    }
+}
+
+// Keeps every observed state in memory
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MemoryObserver {
+    pub time: Vec<f64>,
+    pub current: Vec<f64>,
+    pub voltage: Vec<f64>,
+    pub negative_particle_concentration: Vec<Vec<f64>>,
+    pub positive_particle_concentration: Vec<Vec<f64>>,
+    pub electrolyte_concentration: Vec<Vec<f64>>,
+}
+
+impl MemoryObserver {
+    pub fn new() -> Self {
+        MemoryObserver::default()
+    }
+}
+
+impl Observer for MemoryObserver {
+    fn observe(&mut self, state: &State) -> Result<()> {
+        self.time.push(state.time);
+        self.current.push(state.current);
+        self.voltage.push(state.voltage);
+        self.negative_particle_concentration
+            .push(state.negative_particle_concentration.to_vec());
+        self.positive_particle_concentration
+            .push(state.positive_particle_concentration.to_vec());
+        self.electrolyte_concentration.push(state.electrolyte_concentration.to_vec());
+        Ok(())
+    }
+}
+
+// Writes one line per state with time, current, voltage and the electrolyte concentration profile
+#[derive(Debug)]
+pub struct CsvObserver<W: Write> {
+    writer: W,
+    header_written: bool,
+}
+
+impl<W: Write> CsvObserver<W> {
+    pub fn new(writer: W) -> Self {
+        CsvObserver {
+            writer,
+            header_written: false,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl CsvObserver<BufWriter<File>> {
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        // Truncates any existing file
+        Ok(CsvObserver::new(BufWriter::new(File::create(path)?)))
+    }
+}
+
+impl<W: Write> Observer for CsvObserver<W> {
+    fn observe(&mut self, state: &State) -> Result<()> {
+        if !self.header_written {
+            let mut header: Vec<String> = vec!["time".to_string(), "current".to_string(), "voltage".to_string()];
+            header.extend((0..state.electrolyte_concentration.len()).map(|i| format!("electrolyte_concentration_{i}")));
+            writeln!(self.writer, "{}", header.join(","))?;
+            self.header_written = true;
+        }
+        let line: String = [state.time, state.current, state.voltage]
+            .iter()
+            .chain(state.electrolyte_concentration)
+            .map(|value| value.to_string())
+            .collect::<Vec<String>>()
+            .join(",");
+        writeln!(self.writer, "{}", line)?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+// Passes on the first state, and then the first state at least `interval` seconds after the last one passed on
+#[derive(Debug, Clone)]
+pub struct EveryNSeconds<O: Observer> {
+    pub observer: O,
+    pub interval: f64, // s
+    next_time: Option<f64>,
+}
+
+impl<O: Observer> EveryNSeconds<O> {
+    pub fn new(observer: O, interval: f64) -> Self {
+        EveryNSeconds {
+            observer,
+            interval,
+            next_time: None,
+        }
+    }
+}
+
+impl<O: Observer> Observer for EveryNSeconds<O> {
+    fn observe(&mut self, state: &State) -> Result<()> {
+        if self.next_time.is_some_and(|next_time| state.time < next_time) {
+            return Ok(());
+        }
+        self.next_time = Some(state.time + self.interval);
+        self.observer.observe(state)
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.observer.finish()
+    }
+}
+
+// Passes on every n-th state, starting with the first
+#[derive(Debug, Clone)]
+pub struct Decimate<O: Observer> {
+    pub observer: O,
+    pub factor: NonZeroUsize,
+    count: usize,
+}
+
+impl<O: Observer> Decimate<O> {
+    pub fn new(observer: O, factor: NonZeroUsize) -> Self {
+        Decimate {
+            observer,
+            factor,
+            count: 0,
+        }
+    }
+}
+
+impl<O: Observer> Observer for Decimate<O> {
+    fn observe(&mut self, state: &State) -> Result<()> {
+        let pass: bool = self.count.is_multiple_of(self.factor.get());
+        self.count += 1;
+        if pass {
+            self.observer.observe(state)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.observer.finish()
+    }
+}
@@ -1,50 +1,418 @@
-use crate::model::{Particle, PARTICLE_DISCRETISATION};
+use crate::error::{Error, Result};
+use crate::math::utils::{cubic_hermite, pchip_slopes};
+use crate::model::{Particle, STANDARD_TEMPERATURE};
+
+use std::fmt::Debug;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+// Holds the open circuit potentials of electrode materials, versus Li/Li+. The stoichiometry is the degree of
+// lithiation, x = c/c_max.
+
+pub trait OpenCircuitPotential: Debug + Send + Sync {
+    fn potential(&self, stoichiometry: f64) -> f64;
+
+    // The potential curves are measured at the standard temperature, and shift with the entropic coefficient
+    // $U(x, T) = U(x) + (T - T_{ref}) \frac{dU}{dT}(x)$
+    // Materials with an explicit temperature dependence override this.
+    fn potential_at_temperature(&self, stoichiometry: f64, temperature: f64) -> f64 {
+        self.potential(stoichiometry) + (temperature - STANDARD_TEMPERATURE) * self.entropic_coefficient(stoichiometry)
+    }
+
+    // dU/dx, by central differences unless the material has an analytic derivative
+    fn derivative(&self, stoichiometry: f64) -> f64 {
+        let h: f64 = 1e-6;
+        (self.potential(stoichiometry + h) - self.potential(stoichiometry - h)) / (2.0 * h)
+    }
+
+    // dU/dT in V/K, which gives the reversible heat of the reaction and the temperature shift of the potential.
+    // Zero unless the material provides it, as for the MJ1 materials, whose fits come without dU/dT and for which
+    // no sourced curve is built in yet. Measured data is supplied by implementing this for a wrapper around the
+    // material.
+    fn entropic_coefficient(&self, _stoichiometry: f64) -> f64 {
+        0.0
+    }
+}
+
+// Coefficients of the LG MJ1 fits
+const GRAPHITE_SI_COEFFICIENTS: [f64; 12] = [
+    1.20912055e+00,
+    5.62297420e+01,
+    -1.11020020e-01,
+    -2.53458213e-01,
+    4.92581391e+01,
+    1.22046522e-02,
+    4.73538620e-02,
+    1.79631246e+01,
+    1.75283209e-01,
+    1.88038929e-02,
+    3.03255334e+01,
+    4.66328034e-01,
+];
+const NMC811_COEFFICIENTS: [f64; 11] = [
+    0.74041974,
+    4.39107343,
+    0.03434767,
+    18.16841489,
+    0.53463176,
+    17.68283504,
+    14.59709162,
+    0.28835348,
+    17.58474971,
+    14.69911523,
+    0.28845641,
+];
+
+// Silicon doped graphite of the LG MJ1 18650 cell
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GraphiteSi;
+
+impl OpenCircuitPotential for GraphiteSi {
+    fn potential(&self, x: f64) -> f64 {
+        let p = GRAPHITE_SI_COEFFICIENTS;
+        p[0] * (-p[1] * x).exp() + p[2]
+            - p[3] * (p[4] * (x - p[5])).tanh()
+            - p[6] * (p[7] * (x - p[8])).tanh()
+            - p[9] * (p[10] * (x - p[11])).tanh()
+    }
+}
+
+// NMC811 of the LG MJ1 18650 cell
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Nmc811;
+
+impl OpenCircuitPotential for Nmc811 {
+    fn potential(&self, x: f64) -> f64 {
+        let p = NMC811_COEFFICIENTS;
+        -p[0] * x + p[1] - p[2] * (p[3] * (x - p[4])).tanh() - p[5] * (p[6] * (x - p[7])).tanh()
+            + p[8] * (p[9] * (x - p[10])).tanh()
+    }
+}
+
+// Exponential plus tanh series, the functional form of the LG MJ1 fits,
+// $U = a e^{-b x} + c + m x - \sum_k s_k \tanh(w_k (x - x_k))$
+#[derive(Debug, Clone, PartialEq)]
+pub struct TanhSeries {
+    pub exponential_amplitude: f64, // a
+    pub exponential_rate: f64,      // b
+    pub offset: f64,                // c
+    pub slope: f64,                 // m
+    pub terms: Vec<TanhTerm>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TanhTerm {
+    pub amplitude: f64, // s
+    pub steepness: f64, // w
+    pub centre: f64,    // x_k
+}
+
+impl TanhSeries {
+    pub fn graphite_si() -> Self {
+        let p = GRAPHITE_SI_COEFFICIENTS;
+        TanhSeries {
+            exponential_amplitude: p[0],
+            exponential_rate: p[1],
+            offset: p[2],
+            slope: 0.0,
+            terms: p[3..].chunks(3).map(|t| TanhTerm { amplitude: t[0], steepness: t[1], centre: t[2] }).collect(),
+        }
+    }
+
+    pub fn nmc811() -> Self {
+        let p = NMC811_COEFFICIENTS;
+        let term = |amplitude: f64, t: &[f64]| TanhTerm { amplitude, steepness: t[0], centre: t[1] };
+        TanhSeries {
+            exponential_amplitude: 0.0,
+            exponential_rate: 0.0,
+            offset: p[1],
+            slope: -p[0],
+            terms: vec![term(p[2], &p[3..5]), term(p[5], &p[6..8]), term(-p[8], &p[9..11])],
+        }
+    }
+
+    pub fn coefficients(&self) -> Vec<f64> {
+        // [a, b, c, m, s_1, w_1, x_1, s_2, w_2, x_2, ...]
+        let mut coefficients: Vec<f64> = vec![
+            self.exponential_amplitude,
+            self.exponential_rate,
+            self.offset,
+            self.slope,
+        ];
+        for term in &self.terms {
+            coefficients.extend([term.amplitude, term.steepness, term.centre]);
+        }
+        coefficients
+    }
+
+    pub fn from_coefficients(coefficients: &[f64]) -> Result<Self> {
+        // Inverse of coefficients
+        if coefficients.len() < 4 || !(coefficients.len() - 4).is_multiple_of(3) {
+            return Err(Error::InvalidParameter {
+                parameter: "tanh series coefficients",
+                reason: "expected 4 + 3n coefficients",
+            });
+        }
+        Ok(TanhSeries {
+            exponential_amplitude: coefficients[0],
+            exponential_rate: coefficients[1],
+            offset: coefficients[2],
+            slope: coefficients[3],
+            terms: coefficients[4..]
+                .chunks(3)
+                .map(|t| TanhTerm { amplitude: t[0], steepness: t[1], centre: t[2] })
+                .collect(),
+        })
+    }
+}
+
+impl OpenCircuitPotential for TanhSeries {
+    fn potential(&self, x: f64) -> f64 {
+        self.exponential_amplitude * (-self.exponential_rate * x).exp() + self.offset + self.slope * x
+            - self
+                .terms
+                .iter()
+                .map(|term| term.amplitude * (term.steepness * (x - term.centre)).tanh())
+                .sum::<f64>()
+    }
+
+    fn derivative(&self, x: f64) -> f64 {
+        -self.exponential_rate * self.exponential_amplitude * (-self.exponential_rate * x).exp() + self.slope
+            - self
+                .terms
+                .iter()
+                .map(|term| {
+                    let tanh: f64 = (term.steepness * (x - term.centre)).tanh();
+                    term.amplitude * term.steepness * (1.0 - tanh * tanh)
+                })
+                .sum::<f64>()
+    }
+}
+
+// Graphite, fit of Safari and Delacourt (2011)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Graphite;
+
+impl OpenCircuitPotential for Graphite {
+    fn potential(&self, x: f64) -> f64 {
+        0.6379 + 0.5416 * (-305.5309 * x).exp() + 0.044 * (-(x - 0.1958) / 0.1088).tanh()
+            - 0.1978 * ((x - 1.0571) / 0.0854).tanh()
+            - 0.6875 * ((x + 0.0117) / 0.0529).tanh()
+            - 0.0175 * ((x - 0.5692) / 0.0875).tanh()
+    }
+}
+
+// LiFePO4, fit of Afshar et al. (2017) as used for the cell of Prada et al. (2013)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Lfp;
+
+impl OpenCircuitPotential for Lfp {
+    fn potential(&self, x: f64) -> f64 {
+        3.4077 - 0.020269 * x + 0.5 * (-150.0 * x).exp() - 0.9 * (-30.0 * (1.0 - x)).exp()
+    }
+}
+
+// LiCoO2, fit of Ramadass et al. (2004), valid for 0.5 < x < 1
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Lco;
+
+impl OpenCircuitPotential for Lco {
+    fn potential(&self, x: f64) -> f64 {
+        let x2: f64 = x * x;
+        (-4.656 + x2 * (88.669 + x2 * (-401.119 + x2 * (342.909 + x2 * (-462.471 + x2 * 433.434)))))
+            / (-1.0 + x2 * (18.933 + x2 * (-79.532 + x2 * (37.311 + x2 * (-73.083 + x2 * 95.96)))))
+    }
+}
+
+// LiMn2O4, fit of Doyle et al. (1996), valid for 0.2 < x < 1
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Lmo;
+
+impl OpenCircuitPotential for Lmo {
+    fn potential(&self, x: f64) -> f64 {
+        4.19829 + 0.0565661 * (-14.5546 * x + 8.60942).tanh()
+            - 0.0275479 * (1.0 / (0.998432 - x).powf(0.492465) - 1.90111)
+            - 0.157123 * (-0.04738 * x.powi(8)).exp()
+            + 0.810239 * (-40.0 * (x - 0.133875)).exp()
+    }
+}
+
+// LiNi0.5Mn0.3Co0.2O2, fit of Mohtat et al. (2020)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Nmc532;
+
+impl OpenCircuitPotential for Nmc532 {
+    fn potential(&self, x: f64) -> f64 {
+        4.3452 - 1.6518 * x + 1.6225 * x.powi(2) - 2.0843 * x.powi(3) + 3.5146 * x.powi(4) - 2.2166 * x.powi(5)
+            - 0.5623e-4 * (109.451 * x - 100.006).exp()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Interpolation {
+    Linear,
+    // Monotone piecewise cubic, which does not overshoot measured data
+    #[default]
+    Pchip,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Extrapolation {
+    // Holds the potential at the end points
+    #[default]
+    Constant,
+    // Continues with the slope at the end points
+    Linear,
+}
+
+// Measured curve of potential against stoichiometry, e.g. a pseudo-OCV from a slow cycle or GITT. Materials
+// without a built-in fit, such as NCA, NMC622 and LTO, are loaded this way from published or measured half cell
+// data until sourced curves are added to the crate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TabulatedPotential {
+    stoichiometry: Vec<f64>,
+    potential: Vec<f64>,
+    slopes: Vec<f64>,
+    pub extrapolation: Extrapolation,
+}
+
+impl TabulatedPotential {
+    pub fn new(
+        stoichiometry: Vec<f64>,
+        potential: Vec<f64>,
+        interpolation: Interpolation,
+        extrapolation: Extrapolation,
+    ) -> Result<Self> {
+        // The points may be given in either direction, but every stoichiometry must be unique
+        if stoichiometry.len() != potential.len() {
+            return Err(Error::InvalidTable(format!(
+                "{} stoichiometries and {} potentials",
+                stoichiometry.len(),
+                potential.len()
+            )));
+        }
+        if stoichiometry.len() < 2 {
+            return Err(Error::InvalidTable("at least two points are needed".to_string()));
+        }
+        if stoichiometry.iter().chain(&potential).any(|value| !value.is_finite()) {
+            return Err(Error::InvalidTable("values must be finite".to_string()));
+        }
+        let mut points: Vec<(f64, f64)> = stoichiometry.into_iter().zip(potential).collect();
+        points.sort_by(|a, b| a.0.total_cmp(&b.0));
+        if let Some(w) = points.windows(2).find(|w| w[0].0 == w[1].0) {
+            return Err(Error::InvalidTable(format!("stoichiometry {} appears more than once", w[0].0)));
+        }
+        let (stoichiometry, potential): (Vec<f64>, Vec<f64>) = points.into_iter().unzip();
+
+        let slopes: Vec<f64> = match interpolation {
+            Interpolation::Linear => Vec::new(),
+            Interpolation::Pchip => pchip_slopes(&stoichiometry, &potential),
+        };
+        Ok(TabulatedPotential {
+            stoichiometry,
+            potential,
+            slopes,
+            extrapolation,
+        })
+    }
+
+    pub fn from_csv<P: AsRef<Path>>(path: P, interpolation: Interpolation, extrapolation: Extrapolation) -> Result<Self> {
+        TabulatedPotential::from_reader(BufReader::new(File::open(path)?), interpolation, extrapolation)
+    }
+
+    pub fn from_reader<R: BufRead>(reader: R, interpolation: Interpolation, extrapolation: Extrapolation) -> Result<Self> {
+        // Reads comma separated stoichiometry and potential columns. Empty lines, lines starting with # and a
+        // header line are skipped, and any further columns are ignored.
+        let mut stoichiometry: Vec<f64> = Vec::new();
+        let mut potential: Vec<f64> = Vec::new();
+        let mut header_allowed: bool = true;
+        for (index, line) in reader.lines().enumerate() {
+            let line: String = line?;
+            let line: &str = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let values: Vec<std::result::Result<f64, _>> =
+                line.split(',').take(2).map(|value| value.trim().parse::<f64>()).collect();
+            match values.as_slice() {
+                [Ok(x), Ok(u)] => {
+                    stoichiometry.push(*x);
+                    potential.push(*u);
+                }
+                _ if header_allowed => {}
+                _ => {
+                    return Err(Error::InvalidTable(format!(
+                        "line {} is not a pair of numbers: {line}",
+                        index + 1
+                    )))
+                }
+            }
+            header_allowed = false;
+        }
+        TabulatedPotential::new(stoichiometry, potential, interpolation, extrapolation)
+    }
+
+    pub fn interpolation(&self) -> Interpolation {
+        if self.slopes.is_empty() { Interpolation::Linear } else { Interpolation::Pchip }
+    }
+
+    pub fn points(&self) -> (&[f64], &[f64]) {
+        (&self.stoichiometry, &self.potential)
+    }
+
+    fn evaluate(&self, x: f64) -> (f64, f64) {
+        // Potential and its derivative with respect to stoichiometry
+        let n: usize = self.stoichiometry.len();
+        let (first, last) = (self.stoichiometry[0], self.stoichiometry[n - 1]);
+        if x < first || x > last {
+            let end: usize = if x < first { 0 } else { n - 1 };
+            return match self.extrapolation {
+                Extrapolation::Constant => (self.potential[end], 0.0),
+                Extrapolation::Linear => {
+                    let slope: f64 = self.end_slope(end);
+                    (self.potential[end] + slope * (x - self.stoichiometry[end]), slope)
+                }
+            };
+        }
+
+        // Interval [k, k+1] containing x
+        let k: usize = self.stoichiometry.partition_point(|xk| *xk <= x).clamp(1, n - 1) - 1;
+        let (x0, x1) = (self.stoichiometry[k], self.stoichiometry[k + 1]);
+        let (y0, y1) = (self.potential[k], self.potential[k + 1]);
+        if self.slopes.is_empty() {
+            let slope: f64 = (y1 - y0) / (x1 - x0);
+            (y0 + slope * (x - x0), slope)
+        } else {
+            cubic_hermite(x, x0, x1, y0, y1, self.slopes[k], self.slopes[k + 1])
+        }
+    }
+
+    fn end_slope(&self, end: usize) -> f64 {
+        if !self.slopes.is_empty() {
+            return self.slopes[end];
+        }
+        let k: usize = end.min(self.stoichiometry.len() - 2);
+        (self.potential[k + 1] - self.potential[k]) / (self.stoichiometry[k + 1] - self.stoichiometry[k])
+    }
+}
+
+impl OpenCircuitPotential for TabulatedPotential {
+    fn potential(&self, stoichiometry: f64) -> f64 {
+        self.evaluate(stoichiometry).0
+    }
+
+    fn derivative(&self, stoichiometry: f64) -> f64 {
+        self.evaluate(stoichiometry).1
+    }
+}
+
 // Holds the open circuit voltage functions for LG MJ1 18650 cell
 
 pub fn open_circuit_voltage_graphite_si(particle: &Particle) -> f64 {
-    let c: f64 = particle.concentration[PARTICLE_DISCRETISATION - 1];
-    let c_max: f64 = particle.concentration_max;
-    let x: f64 = c / c_max;
-
-    let p = [
-        1.20912055e+00,
-        5.62297420e+01,
-        -1.11020020e-01,
-        -2.53458213e-01,
-        4.92581391e+01,
-        1.22046522e-02,
-        4.73538620e-02,
-        1.79631246e+01,
-        1.75283209e-01,
-        1.88038929e-02,
-        3.03255334e+01,
-        4.66328034e-01,
-    ];
-
-    p[0] * (-p[1] * x).exp() + p[2]
-        - p[3] * (p[4] * (x - p[5])).tanh()
-        - p[6] * (p[7] * (x - p[8])).tanh()
-        - p[9] * (p[10] * (x - p[11])).tanh()
+    GraphiteSi.potential(particle.surface_concentration() / particle.concentration_max)
 }
+
 pub fn open_circuit_voltage_nmc811(particle: &Particle) -> f64 {
-    let c: f64 = particle.concentration[PARTICLE_DISCRETISATION - 1];
-    let c_max: f64 = particle.concentration_max;
-    let x = c / c_max;
-
-    let p = [
-        0.74041974,
-        4.39107343,
-        0.03434767,
-        18.16841489,
-        0.53463176,
-        17.68283504,
-        14.59709162,
-        0.28835348,
-        17.58474971,
-        14.69911523,
-        0.28845641,
-    ];
-
-    -p[0] * x + p[1] - p[2] * (p[3] * (x - p[4])).tanh() - p[5] * (p[6] * (x - p[7])).tanh()
-        + p[8] * (p[9] * (x - p[10])).tanh()
+    Nmc811.potential(particle.surface_concentration() / particle.concentration_max)
 }
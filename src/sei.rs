@@ -0,0 +1,124 @@
+use crate::model::{FARADAY, GAS_CONSTANT};
+
+// Growth of the solid electrolyte interphase (SEI) on the particles of the negative electrode. The side reaction
+// runs in parallel to intercalation at the particle surface, and each SEI molecule binds z electrons and lithium
+// ions, so the film grows as
+// $\frac{dL}{dt} = \frac{j \bar{V}}{z F}$
+// where j is the side reaction current density at the particle surface. The lithium bound in the film is lost to
+// cycling, and the ionic resistance of the film adds an overpotential $i L \rho$ to the electrode.
+// The growth law sets which process limits j:
+// - solvent diffusion through the film, $j = \frac{F D_{sol} c_{sol}}{L}$
+// - the reaction at the film surface, $j = F k c_{sol} \exp\left(-\frac{\alpha F (\phi - U_{sei})}{R T}\right)$
+// - diffusion of lithium interstitials through the film,
+//   $j = \frac{F D_{int} c_{int}}{L} \exp\left(-\frac{F \phi}{R T}\right)$
+// where $\phi$ is the potential of the electrode versus Li/Li+. Each growth law carries its own parameters.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SeiGrowth {
+    SolventDiffusionLimited {
+        solvent_diffusivity: f64,   // m^2/s, in the film
+        solvent_concentration: f64, // mol/m^3, in the electrolyte
+    },
+    ReactionLimited {
+        rate_constant: f64,          // m/s
+        solvent_concentration: f64,  // mol/m^3, in the electrolyte
+        open_circuit_potential: f64, // V, of the side reaction versus Li/Li+
+        transfer_coefficient: f64,   // alpha, of the side reaction
+    },
+    InterstitialDiffusionLimited {
+        interstitial_diffusivity: f64,   // m^2/s
+        interstitial_concentration: f64, // mol/m^3, at the particle surface
+    },
+}
+
+// Rate parameters of the order that gives a few percent capacity loss per year of storage, in a carbonate
+// electrolyte
+impl SeiGrowth {
+    pub fn solvent_diffusion_limited() -> Self {
+        SeiGrowth::SolventDiffusionLimited {
+            solvent_diffusivity: 2.5e-22,  // m^2/s
+            solvent_concentration: 2636.0, // mol/m^3
+        }
+    }
+
+    pub fn reaction_limited() -> Self {
+        SeiGrowth::ReactionLimited {
+            rate_constant: 1e-16,          // m/s
+            solvent_concentration: 2636.0, // mol/m^3
+            open_circuit_potential: 0.4,   // V
+            transfer_coefficient: 0.5,
+        }
+    }
+
+    pub fn interstitial_diffusion_limited() -> Self {
+        SeiGrowth::InterstitialDiffusionLimited {
+            interstitial_diffusivity: 1e-21,  // m^2/s
+            interstitial_concentration: 15.0, // mol/m^3
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sei {
+    pub growth: SeiGrowth,
+    pub thickness: f64,              // m, state of the film
+    pub lost_lithium: f64,           // mol, state, lithium bound in the film grown so far
+    pub molar_volume: f64,           // m^3/mol
+    pub electrons_per_molecule: f64, // z
+    pub resistivity: f64,            // Ohm*m, ionic resistivity of the film
+}
+
+impl Sei {
+    pub fn new(growth: SeiGrowth) -> Self {
+        // A fresh film of a few nm on graphite
+        Sei {
+            growth,
+            thickness: 5e-9,        // m
+            lost_lithium: 0.0,
+            molar_volume: 9.585e-5, // m^3/mol
+            electrons_per_molecule: 2.0,
+            resistivity: 2e5,       // Ohm*m
+        }
+    }
+
+    pub fn current_density(&self, electrode_potential: f64, temperature: f64) -> f64 {
+        // Side reaction current density at the particle surface, A/m^2, positive as it consumes lithium.
+        // The electrode potential is that of the solid versus Li/Li+, including the reaction overpotential.
+        let f: f64 = FARADAY / (GAS_CONSTANT * temperature);
+        match self.growth {
+            SeiGrowth::SolventDiffusionLimited {
+                solvent_diffusivity,
+                solvent_concentration,
+            } => FARADAY * solvent_diffusivity * solvent_concentration / self.thickness,
+            SeiGrowth::ReactionLimited {
+                rate_constant,
+                solvent_concentration,
+                open_circuit_potential,
+                transfer_coefficient,
+            } => {
+                let overpotential: f64 = electrode_potential - open_circuit_potential;
+                FARADAY * rate_constant * solvent_concentration * (-transfer_coefficient * f * overpotential).exp()
+            }
+            SeiGrowth::InterstitialDiffusionLimited {
+                interstitial_diffusivity,
+                interstitial_concentration,
+            } => {
+                FARADAY * interstitial_diffusivity * interstitial_concentration / self.thickness
+                    * (-f * electrode_potential).exp()
+            }
+        }
+    }
+
+    pub fn film_resistance(&self) -> f64 {
+        // Ohm*m^2 of particle surface
+        self.thickness * self.resistivity
+    }
+
+    pub fn step(&mut self, current_density: f64, surface_area: f64, dt: f64) {
+        // Forward euler over a timestep at constant side reaction current density, where the surface area is the
+        // particle surface of the whole electrode in m^2
+        let molecules: f64 = current_density * dt / (self.electrons_per_molecule * FARADAY); // mol/m^2
+        self.thickness += molecules * self.molar_volume;
+        self.lost_lithium += self.electrons_per_molecule * molecules * surface_area;
+    }
+}
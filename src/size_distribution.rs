@@ -0,0 +1,69 @@
+// Particle size distributions, discretised into a number of particle classes per electrode. Each class is
+// stepped as its own particle, see Electrode::set_size_distribution.
+
+use crate::error::{Error, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LognormalDistribution {
+    pub mean_radius: f64,        // m
+    pub standard_deviation: f64, // m, of the radius
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SizeClass {
+    pub radius: f64,       // m
+    pub volume_share: f64, // share of the active material volume held by the class
+}
+
+impl LognormalDistribution {
+    pub fn new(mean_radius: f64, standard_deviation: f64) -> Self {
+        LognormalDistribution {
+            mean_radius,
+            standard_deviation,
+        }
+    }
+
+    pub fn classes(&self, classes: usize) -> Result<Vec<SizeClass>> {
+        // The logarithm of the radius is normally distributed with
+        // $\sigma^2 = \ln \left( 1 + \frac{s^2}{\bar{r}^2} \right)$ and $\mu = \ln \bar{r} - \frac{\sigma^2}{2}$
+        // Classes are equally wide in ln(r) over three standard deviations to either side. The number of particles
+        // in a class follows the density at its centre, and the volume it holds grows with the radius cubed.
+        if classes == 0 {
+            return Err(Error::InvalidParameter {
+                parameter: "size distribution",
+                reason: "at least one particle class is needed",
+            });
+        }
+        if !(self.mean_radius > 0.0 && self.mean_radius.is_finite()) {
+            return Err(Error::InvalidParameter {
+                parameter: "size distribution",
+                reason: "the mean radius must be positive and finite",
+            });
+        }
+        if !(self.standard_deviation >= 0.0 && self.standard_deviation.is_finite()) {
+            return Err(Error::InvalidParameter {
+                parameter: "size distribution",
+                reason: "the standard deviation must be non-negative and finite",
+            });
+        }
+        let variance: f64 = (1.0 + (self.standard_deviation / self.mean_radius).powi(2)).ln();
+        let mu: f64 = self.mean_radius.ln() - 0.5 * variance;
+        let sigma: f64 = variance.sqrt();
+
+        let mut classes: Vec<SizeClass> = (0..classes)
+            .map(|k| {
+                let z: f64 = -3.0 + 6.0 * (k as f64 + 0.5) / classes as f64;
+                let radius: f64 = (mu + sigma * z).exp();
+                SizeClass {
+                    radius,
+                    volume_share: (-0.5 * z * z).exp() * radius.powi(3),
+                }
+            })
+            .collect();
+        let total: f64 = classes.iter().map(|class| class.volume_share).sum();
+        for class in classes.iter_mut() {
+            class.volume_share /= total;
+        }
+        Ok(classes)
+    }
+}
@@ -0,0 +1,188 @@
+// Holds the output of a simulation. The terminal voltage is built from the stored contributions as
+// $V = U_p - U_n - \eta_n - \eta_p + \eta_c + \eta_{ohm,s} + \eta_{ohm,e} + I R_{contact} + \eta_{film}$
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OutputSpecification {
+    // Radial concentration profile of both particles at every output time
+    pub particle_concentration: bool,
+    // Electrolyte concentration profile from the negative to the positive current collector at every output time
+    pub electrolyte_concentration: bool,
+}
+
+impl OutputSpecification {
+    pub fn all() -> Self {
+        OutputSpecification {
+            particle_concentration: true,
+            electrolyte_concentration: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ElectrodeSolution {
+    pub open_circuit_potential: Vec<f64>, // V
+    pub surface_overpotential: Vec<f64>,  // V
+    // The stoichiometries of a blended electrode or size distribution are weighted by the capacity of each
+    // particle, and the particle profiles are averaged by volume fraction over the relative radius, on the mesh of
+    // the primary particle
+    pub surface_stoichiometry: Vec<f64>,
+    pub average_stoichiometry: Vec<f64>,
+    pub particle_concentration: Option<Vec<Vec<f64>>>, // mol/m^3, centre to surface
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Solution {
+    pub time: Vec<f64>,        // s
+    pub current: Vec<f64>,     // A, positive on charge
+    pub voltage: Vec<f64>,     // V
+    pub temperature: Vec<f64>, // K
+    pub negative_electrode: ElectrodeSolution,
+    pub positive_electrode: ElectrodeSolution,
+    pub electrolyte_concentration_overpotential: Vec<f64>, // V
+    pub solid_ohmic_overpotential: Vec<f64>,               // V
+    pub electrolyte_ohmic_overpotential: Vec<f64>,         // V
+    pub contact_overpotential: Vec<f64>,                   // V
+    pub film_overpotential: Vec<f64>,                      // V, of the SEI on the negative electrode
+    pub electrolyte_concentration: Option<Vec<Vec<f64>>>,  // mol/m^3
+}
+
+impl Solution {
+    pub fn new(output: &OutputSpecification) -> Self {
+        let profiles = |enabled: bool| if enabled { Some(Vec::new()) } else { None };
+        let electrode = || ElectrodeSolution {
+            particle_concentration: profiles(output.particle_concentration),
+            ..ElectrodeSolution::default()
+        };
+        Solution {
+            negative_electrode: electrode(),
+            positive_electrode: electrode(),
+            electrolyte_concentration: profiles(output.electrolyte_concentration),
+            ..Solution::default()
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.time.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.time.is_empty()
+    }
+
+    pub fn open_circuit_voltage(&self) -> Vec<f64> {
+        // Difference between the open circuit potentials of the positive and negative electrodes
+        self.positive_electrode
+            .open_circuit_potential
+            .iter()
+            .zip(&self.negative_electrode.open_circuit_potential)
+            .map(|(p, n)| p - n)
+            .collect()
+    }
+
+    pub(crate) fn push(&mut self, time: f64, current: f64, sample: &Sample) {
+        self.time.push(time);
+        self.current.push(current);
+        self.voltage.push(sample.voltage());
+        self.temperature.push(sample.temperature);
+        for (electrode, state) in [
+            (&mut self.negative_electrode, &sample.negative_electrode),
+            (&mut self.positive_electrode, &sample.positive_electrode),
+        ] {
+            electrode.open_circuit_potential.push(state.open_circuit_potential);
+            electrode.surface_overpotential.push(state.surface_overpotential);
+            electrode.surface_stoichiometry.push(state.surface_stoichiometry);
+            electrode.average_stoichiometry.push(state.average_stoichiometry);
+            if let Some(profiles) = &mut electrode.particle_concentration {
+                profiles.push(state.particle_concentration.clone());
+            }
+        }
+        self.electrolyte_concentration_overpotential
+            .push(sample.electrolyte_concentration_overpotential);
+        self.solid_ohmic_overpotential.push(sample.solid_ohmic_overpotential);
+        self.electrolyte_ohmic_overpotential.push(sample.electrolyte_ohmic_overpotential);
+        self.contact_overpotential.push(sample.contact_overpotential);
+        self.film_overpotential.push(sample.film_overpotential);
+        if let Some(profiles) = &mut self.electrolyte_concentration {
+            profiles.push(sample.electrolyte_concentration.clone());
+        }
+    }
+}
+
+// Model output at a single instant, profiles are left empty when they are not requested
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ElectrodeSample {
+    pub open_circuit_potential: f64,
+    pub surface_overpotential: f64,
+    pub surface_stoichiometry: f64,
+    pub average_stoichiometry: f64,
+    pub particle_concentration: Vec<f64>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Sample {
+    pub temperature: f64,
+    pub negative_electrode: ElectrodeSample,
+    pub positive_electrode: ElectrodeSample,
+    pub electrolyte_concentration_overpotential: f64,
+    pub solid_ohmic_overpotential: f64,
+    pub electrolyte_ohmic_overpotential: f64,
+    pub contact_overpotential: f64,
+    pub film_overpotential: f64,
+    pub electrolyte_concentration: Vec<f64>,
+}
+
+fn lerp(a: f64, b: f64, w: f64) -> f64 {
+    a + w * (b - a)
+}
+
+fn lerp_profile(a: &[f64], b: &[f64], w: f64) -> Vec<f64> {
+    a.iter().zip(b).map(|(a, b)| lerp(*a, *b, w)).collect()
+}
+
+impl ElectrodeSample {
+    fn interpolate(&self, other: &ElectrodeSample, w: f64) -> ElectrodeSample {
+        ElectrodeSample {
+            open_circuit_potential: lerp(self.open_circuit_potential, other.open_circuit_potential, w),
+            surface_overpotential: lerp(self.surface_overpotential, other.surface_overpotential, w),
+            surface_stoichiometry: lerp(self.surface_stoichiometry, other.surface_stoichiometry, w),
+            average_stoichiometry: lerp(self.average_stoichiometry, other.average_stoichiometry, w),
+            particle_concentration: lerp_profile(&self.particle_concentration, &other.particle_concentration, w),
+        }
+    }
+}
+
+impl Sample {
+    pub fn voltage(&self) -> f64 {
+        self.positive_electrode.open_circuit_potential - self.negative_electrode.open_circuit_potential
+            - self.negative_electrode.surface_overpotential
+            - self.positive_electrode.surface_overpotential
+            + self.electrolyte_concentration_overpotential
+            + self.solid_ohmic_overpotential
+            + self.electrolyte_ohmic_overpotential
+            + self.contact_overpotential
+            + self.film_overpotential
+    }
+
+    pub fn interpolate(&self, other: &Sample, w: f64) -> Sample {
+        // Linear interpolation between two samples, w = 0 gives self and w = 1 gives other
+        Sample {
+            temperature: lerp(self.temperature, other.temperature, w),
+            negative_electrode: self.negative_electrode.interpolate(&other.negative_electrode, w),
+            positive_electrode: self.positive_electrode.interpolate(&other.positive_electrode, w),
+            electrolyte_concentration_overpotential: lerp(
+                self.electrolyte_concentration_overpotential,
+                other.electrolyte_concentration_overpotential,
+                w,
+            ),
+            solid_ohmic_overpotential: lerp(self.solid_ohmic_overpotential, other.solid_ohmic_overpotential, w),
+            electrolyte_ohmic_overpotential: lerp(
+                self.electrolyte_ohmic_overpotential,
+                other.electrolyte_ohmic_overpotential,
+                w,
+            ),
+            contact_overpotential: lerp(self.contact_overpotential, other.contact_overpotential, w),
+            film_overpotential: lerp(self.film_overpotential, other.film_overpotential, w),
+            electrolyte_concentration: lerp_profile(&self.electrolyte_concentration, &other.electrolyte_concentration, w),
+        }
+    }
+}
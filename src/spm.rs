@@ -0,0 +1,210 @@
+use crate::error::{Error, Result};
+use crate::math::numerical_methods::TimeScheme;
+use crate::model::{
+    check_input, check_particle_stability, check_surface_stoichiometry, exchange_current_density, solve_fixed,
+    Electrode, FixedStepping, Particle, Region, SPMeModel, FARADAY, STANDARD_TEMPERATURE,
+};
+use crate::observer::{Observer, State};
+use crate::solution::{ElectrodeSample, OutputSpecification, Sample, Solution};
+use crate::Simulate;
+
+// Single particle model, the SPMe without electrolyte dynamics. The electrolyte concentration is held constant, so
+// only the particles are stepped in time and the timestep is limited by particle diffusion alone. Each electrode
+// uses its primary particle with quasi-static kinetics at the standard temperature, so double layers, blends, SEI
+// and the thermal model are not supported, neither on conversion nor when set on the electrodes afterwards, and the
+// electrolyte contributions to the voltage are zero. Meant for fast simulation, e.g. state estimation.
+
+#[derive(Debug, Clone)]
+pub struct SPMModel {
+    pub negative_electrode: Electrode,
+    pub positive_electrode: Electrode,
+    pub electrolyte_concentration: f64, // mol/m^3, used by the kinetics
+    pub time_scheme: TimeScheme,
+    pub contact_resistance: f64, // Ohm, lumped contact and tab resistance
+}
+
+impl TryFrom<SPMeModel> for SPMModel {
+    type Error = Error;
+
+    fn try_from(model: SPMeModel) -> Result<Self> {
+        // Takes the electrodes and time scheme of an SPMe model, at its present average electrolyte concentration
+        model.check_quasi_static("SPM model")?;
+        let concentration: &[f64] = &model.electrolyte.concentration;
+        Ok(SPMModel {
+            electrolyte_concentration: concentration.iter().sum::<f64>() / concentration.len() as f64,
+            negative_electrode: model.negative_electrode,
+            positive_electrode: model.positive_electrode,
+            time_scheme: model.time_scheme,
+            contact_resistance: model.contact_resistance,
+        })
+    }
+}
+
+impl Default for SPMModel {
+    fn default() -> Self {
+        // The MJ1 cell of the SPMe, with Crank-Nicolson to allow timesteps of seconds
+        let model: SPMModel = SPMModel::try_from(SPMeModel::quasi_static())
+            .expect("The default SPMe model only uses parts of the SPM model");
+        SPMModel {
+            time_scheme: TimeScheme::CrankNicolson,
+            ..model
+        }
+    }
+}
+
+impl SPMModel {
+    fn electrode(&self, region: Region) -> &Electrode {
+        match region {
+            Region::NegativeElectrode => &self.negative_electrode,
+            Region::PositiveElectrode => &self.positive_electrode,
+            Region::Separator => unreachable!("The separator is not an electrode"),
+        }
+    }
+
+    fn reaction_area(electrode: &Electrode) -> f64 {
+        // m^2 of particle surface per m^2 of electrode, $a L$ with the specific surface area of the SPMe
+        SPMeModel::specific_interfacial_surface_area(electrode) * electrode.thickness
+    }
+
+    fn surface_overpotential(&self, current: f64, region: Region) -> Result<f64> {
+        // Quasi-static reaction overpotential, in the model convention where charge is positive
+        let electrode: &Electrode = self.electrode(region);
+        let p: f64 = SPMeModel::polarity(region);
+        let current_density: f64 = current / (electrode.height * electrode.width); // A/m^2
+        let eta: f64 = electrode.kinetics.overpotential(
+            exchange_current_density(
+                &electrode.kinetics,
+                electrode.reaction_rate_constant,
+                &electrode.particle,
+                self.electrolyte_concentration,
+            ),
+            p * current_density / SPMModel::reaction_area(electrode),
+            STANDARD_TEMPERATURE,
+        )?;
+        Ok(- p * eta)
+    }
+
+    fn sample(&self, current: f64, output: &OutputSpecification) -> Result<Sample> {
+        let cell_area: f64 = self.negative_electrode.height * self.negative_electrode.width;
+        let current_density: f64 = current / cell_area; // A/m^2
+
+        let electrode_sample = |region: Region| -> Result<ElectrodeSample> {
+            let electrode: &Electrode = self.electrode(region);
+            let particle: &Particle = &electrode.particle;
+            let primary_stoichiometry: f64 = particle.surface_concentration() / particle.concentration_max;
+            Ok(ElectrodeSample {
+                open_circuit_potential: electrode.open_circuit_potential_at(primary_stoichiometry, STANDARD_TEMPERATURE),
+                surface_overpotential: self.surface_overpotential(current, region)?,
+                surface_stoichiometry: electrode.surface_stoichiometry(),
+                average_stoichiometry: electrode.average_stoichiometry(),
+                particle_concentration: if output.particle_concentration {
+                    electrode.particle_concentration()
+                } else {
+                    Vec::new()
+                },
+            })
+        };
+
+        Ok(Sample {
+            temperature: STANDARD_TEMPERATURE,
+            negative_electrode: electrode_sample(Region::NegativeElectrode)?,
+            positive_electrode: electrode_sample(Region::PositiveElectrode)?,
+            electrolyte_concentration_overpotential: 0.0,
+            // Ohmic overpotential in solid, as in the SPMe
+            solid_ohmic_overpotential: SPMeModel::solid_ohmic_overpotential(
+                &self.negative_electrode,
+                &self.positive_electrode,
+                current_density,
+            ),
+            electrolyte_ohmic_overpotential: 0.0,
+            contact_overpotential: current * self.contact_resistance,
+            film_overpotential: 0.0,
+            // There is no electrolyte profile
+            electrolyte_concentration: Vec::new(),
+        })
+    }
+
+    fn step(&mut self, dt: f64, current: f64) {
+        // Steps the particle concentrations forward by one timestep at constant current
+        let time_scheme: TimeScheme = self.time_scheme;
+        for region in [Region::NegativeElectrode, Region::PositiveElectrode] {
+            let electrode: &mut Electrode = match region {
+                Region::NegativeElectrode => &mut self.negative_electrode,
+                _ => &mut self.positive_electrode,
+            };
+            let stoichiometries: Vec<f64> = electrode.stoichiometries();
+            // Positive flux leaves the particle, so charge lithiates the negative particle
+            let current_density: f64 = current / (electrode.height * electrode.width); // A/m^2
+            let flux: f64 = SPMeModel::polarity(region) * current_density / ( FARADAY * SPMModel::reaction_area(electrode) );
+            let particle: &mut Particle = &mut electrode.particle;
+            time_scheme.step_radial(
+                &mut particle.concentration,
+                particle.dr,
+                dt,
+                particle.diffusion_coeff,
+                particle.radius,
+                flux, // flux
+            );
+            electrode.update_hysteresis(&stoichiometries);
+        }
+    }
+}
+
+impl FixedStepping for SPMModel {
+    type Input = f64;
+
+    fn time_scheme(&self) -> TimeScheme {
+        self.time_scheme
+    }
+
+    fn check_ftcs_stability(&self, dt: f64) -> Result<()> {
+        // Only the primary particles are stepped
+        for region in [Region::NegativeElectrode, Region::PositiveElectrode] {
+            check_particle_stability(region, [&self.electrode(region).particle], 1.0, dt)?;
+        }
+        Ok(())
+    }
+
+    fn step_sample(
+        &mut self,
+        dt: f64,
+        current: f64,
+        _previous_current: f64,
+        time: f64,
+        output: &OutputSpecification,
+    ) -> Result<(f64, Sample)> {
+        self.step(dt, current);
+        for region in [Region::NegativeElectrode, Region::PositiveElectrode] {
+            check_surface_stoichiometry(region, [&self.electrode(region).particle], time)?;
+        }
+        Ok((current, self.sample(current, output)?))
+    }
+
+    fn observe(&self, time: f64, current: f64, sample: &Sample, observer: &mut dyn Observer) -> Result<()> {
+        observer.observe(&State {
+            time,
+            current,
+            voltage: sample.voltage(),
+            negative_particle_concentration: &self.negative_electrode.particle.concentration,
+            positive_particle_concentration: &self.positive_electrode.particle.concentration,
+            electrolyte_concentration: &[],
+        })
+    }
+}
+
+impl Simulate for SPMModel {
+    fn solve_observed(
+        &mut self,
+        time: &[f64],
+        current: &[f64],
+        output: &OutputSpecification,
+        observer: &mut dyn Observer,
+    ) -> Result<Solution> {
+        check_input(time, current)?;
+        self.negative_electrode.check_quasi_static("SPM model")?;
+        self.positive_electrode.check_quasi_static("SPM model")?;
+        let solution: Solution = solve_fixed(self, time, current, output, observer)?;
+        observer.finish()?;
+        Ok(solution)
+    }
+}
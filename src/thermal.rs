@@ -0,0 +1,76 @@
+use crate::model::{GAS_CONSTANT, STANDARD_TEMPERATURE};
+
+// Lumped thermal model, where the whole cell is at one temperature given by the energy balance
+// $C_{th} \frac{dT}{dt} = Q_{irr} + Q_{rev} + Q_{ohm} - h A (T - T_{amb})$
+// with irreversible heat from the reaction and concentration overpotentials, reversible heat from the entropy
+// change of the electrode reactions, ohmic heat in solid, electrolyte and contacts, and convective cooling.
+// Transport and kinetic parameters follow the temperature by Arrhenius laws, relative to their values at
+// the standard temperature.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ThermalModel {
+    pub temperature: f64,               // K, state of the cell
+    pub ambient_temperature: f64,       // K
+    pub heat_capacity: f64,             // J/K, of the whole cell
+    pub heat_transfer_coefficient: f64, // W/(m^2*K), convection to ambient
+    pub cooling_area: f64,              // m^2
+    pub activation_energies: ActivationEnergies,
+}
+
+// Activation energies in J/mol, zero for a parameter that does not depend on temperature
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ActivationEnergies {
+    pub negative_diffusion: f64,
+    pub positive_diffusion: f64,
+    pub negative_reaction: f64,
+    pub positive_reaction: f64,
+    pub electrolyte_diffusion: f64,
+    pub electrolyte_conductivity: f64,
+}
+
+// Heat generation rates in W, positive when heat is released in the cell
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct HeatGeneration {
+    pub irreversible: f64,
+    pub reversible: f64,
+    pub ohmic: f64,
+}
+
+impl Default for ThermalModel {
+    fn default() -> Self {
+        // 18650 cell of about 47 g in still air, with typical activation energies for graphite, layered oxides
+        // and carbonate electrolytes
+        ThermalModel {
+            temperature: STANDARD_TEMPERATURE,
+            ambient_temperature: STANDARD_TEMPERATURE,
+            heat_capacity: 47.0,             // J/K
+            heat_transfer_coefficient: 10.0, // W/(m^2*K)
+            cooling_area: 4.2e-3,            // m^2, mantle and ends
+            activation_energies: ActivationEnergies {
+                negative_diffusion: 30e3,
+                positive_diffusion: 25e3,
+                negative_reaction: 35e3,
+                positive_reaction: 17.8e3,
+                electrolyte_diffusion: 17e3,
+                electrolyte_conductivity: 17e3,
+            },
+        }
+    }
+}
+
+impl ThermalModel {
+    pub fn arrhenius(&self, activation_energy: f64) -> f64 {
+        // Factor on a parameter at the present temperature, relative to the standard temperature
+        // $\exp\left(\frac{E_a}{R} \left(\frac{1}{T_{ref}} - \frac{1}{T}\right)\right)$
+        (activation_energy / GAS_CONSTANT * (1.0 / STANDARD_TEMPERATURE - 1.0 / self.temperature)).exp()
+    }
+
+    pub fn step(&mut self, heat: &HeatGeneration, dt: f64) {
+        // Backward euler in the cooling term, which keeps the temperature from overshooting the ambient
+        let generated: f64 = heat.irreversible + heat.reversible + heat.ohmic;
+        let conductance: f64 = self.heat_transfer_coefficient * self.cooling_area; // W/K
+        let heat_flow: f64 = generated + conductance * self.ambient_temperature; // W
+        self.temperature =
+            (self.temperature + dt / self.heat_capacity * heat_flow) / (1.0 + dt * conductance / self.heat_capacity);
+    }
+}
@@ -0,0 +1,129 @@
+use pxd::hysteresis::Hysteresis;
+use pxd::model::{ActiveMaterial, Particle, SPMeModel};
+use pxd::ocv::{Graphite, OpenCircuitPotential};
+use pxd::{OutputSpecification, Simulate, Solution};
+
+use std::sync::Arc;
+
+mod common;
+use common::model;
+
+// Simple monotonic silicon curve, only meant to differ clearly from graphite
+#[derive(Debug)]
+struct Silicon;
+
+impl OpenCircuitPotential for Silicon {
+    fn potential(&self, stoichiometry: f64) -> f64 {
+        0.1 + 0.6 * (1.0 - stoichiometry).powi(2)
+    }
+}
+
+fn graphite_silicon() -> SPMeModel {
+    let mut model: SPMeModel = model();
+    model.negative_electrode.open_circuit_potential = Arc::new(Graphite);
+    model.negative_electrode.particle = Particle::new(6.1e-6, 5e-14, 29000.0, 0.03 * 29000.0);
+    model.negative_electrode.active_material_volume_fraction = 0.66;
+    model.negative_electrode.blend.push(ActiveMaterial {
+        particle: Particle::new(1e-6, 1e-14, 278000.0, 0.05 * 278000.0),
+        open_circuit_potential: Arc::new(Silicon),
+        hysteresis: None,
+        volume_fraction: 0.03,
+        reaction_rate_constant: 1e-4,
+    });
+    model
+}
+
+fn lithium_in_negative_electrode(model: &SPMeModel) -> f64 {
+    // mol
+    let electrode = &model.negative_electrode;
+    let volume: f64 = electrode.height * electrode.width * electrode.thickness;
+    let fractions = std::iter::once(electrode.active_material_volume_fraction)
+        .chain(electrode.blend.iter().map(|material| material.volume_fraction));
+    electrode
+        .particles()
+        .zip(fractions)
+        .map(|(particle, fraction)| particle.average_concentration() * fraction * volume)
+        .sum()
+}
+
+#[test]
+fn evenly_split_material_matches_single_material() {
+    let time: Vec<f64> = (0..=600).map(|t| t as f64).collect();
+    let current: Vec<f64> = vec![3.0; time.len()];
+    let mut single: SPMeModel = model();
+    let mut blend: SPMeModel = model();
+    let electrode = &mut blend.negative_electrode;
+    electrode.active_material_volume_fraction *= 0.5;
+    electrode.blend.push(ActiveMaterial {
+        particle: electrode.particle.clone(),
+        open_circuit_potential: electrode.open_circuit_potential.clone(),
+        hysteresis: None,
+        volume_fraction: electrode.active_material_volume_fraction,
+        reaction_rate_constant: electrode.reaction_rate_constant,
+    });
+
+    let expected: Vec<f64> = single.simulate(&time, &current);
+    let voltage: Vec<f64> = blend.simulate(&time, &current);
+    for (v, e) in voltage.iter().zip(&expected) {
+        assert!((v - e).abs() < 1e-8, "{v} vs {e}");
+    }
+}
+
+#[test]
+fn blend_conserves_lithium_and_relaxes_to_common_potential() {
+    let mut model: SPMeModel = graphite_silicon();
+    let initial: f64 = lithium_in_negative_electrode(&model);
+    let time: Vec<f64> = (0..=360).map(|t| t as f64 * 5.0).collect();
+    let voltage: Vec<f64> = model.simulate(&time, &vec![3.0; time.len()]);
+    assert!(voltage.iter().all(|v| v.is_finite()));
+    let charge: f64 = 3.0 * 1800.0 / 96485.33212331001;
+    let charged: f64 = lithium_in_negative_electrode(&model);
+    assert!(((charged - initial) / charge - 1.0).abs() < 0.05, "{} vs {charge}", charged - initial);
+
+    // At rest lithium only moves between the materials, until they sit at the same potential. The tolerance
+    // allows for the radial averaging, which differs slightly between the particle meshes.
+    let silicon_charged: f64 = model.negative_electrode.blend[0].particle.average_concentration();
+    let time: Vec<f64> = (0..=720).map(|t| t as f64 * 5.0).collect();
+    model.simulate(&time, &vec![0.0; time.len()]);
+    let rested: f64 = lithium_in_negative_electrode(&model);
+    assert!((rested / charged - 1.0).abs() < 5e-3, "{rested} vs {charged}");
+
+    let graphite: &Particle = &model.negative_electrode.particle;
+    let silicon: &Particle = &model.negative_electrode.blend[0].particle;
+    let u_graphite: f64 = Graphite.potential(graphite.surface_concentration() / graphite.concentration_max);
+    let u_silicon: f64 = Silicon.potential(silicon.surface_concentration() / silicon.concentration_max);
+    assert!((u_graphite - u_silicon).abs() < 5e-3, "{u_graphite} vs {u_silicon}");
+    assert!(silicon.average_concentration() != silicon_charged);
+}
+
+#[test]
+fn sampled_stoichiometry_covers_all_materials() {
+    let mut model: SPMeModel = graphite_silicon();
+    let time: Vec<f64> = (0..=120).map(|t| t as f64 * 5.0).collect();
+    let solution: Solution = model.solve(&time, &vec![3.0; time.len()], &OutputSpecification::default()).unwrap();
+    let electrode = &model.negative_electrode;
+    let fractions = std::iter::once(electrode.active_material_volume_fraction)
+        .chain(electrode.blend.iter().map(|material| material.volume_fraction));
+    let (lithium, capacity): (f64, f64) = electrode
+        .particles()
+        .zip(fractions)
+        .fold((0.0, 0.0), |(lithium, capacity), (particle, fraction)| {
+            (lithium + fraction * particle.average_concentration(), capacity + fraction * particle.concentration_max)
+        });
+    let sampled: f64 = *solution.negative_electrode.average_stoichiometry.last().unwrap();
+    assert!((sampled - lithium / capacity).abs() < 1e-12, "{sampled} vs {}", lithium / capacity);
+    let graphite: &Particle = &electrode.particle;
+    assert!((sampled - graphite.average_concentration() / graphite.concentration_max).abs() > 1e-3);
+}
+
+#[test]
+fn blend_material_follows_its_own_hysteresis() {
+    // Lithiation shifts the silicon onto its lithiation branch
+    let mut model: SPMeModel = graphite_silicon();
+    let silicon: Arc<dyn OpenCircuitPotential> = Arc::new(Silicon);
+    model.negative_electrode.blend[0].hysteresis = Some(Hysteresis::new(silicon.clone(), silicon, 50.0));
+    let time: Vec<f64> = (0..=120).map(|t| t as f64 * 5.0).collect();
+    model.simulate(&time, &vec![3.0; time.len()]);
+    let state: f64 = model.negative_electrode.blend[0].hysteresis.as_ref().unwrap().state;
+    assert!(state < -0.5, "{state}");
+}
@@ -0,0 +1,10 @@
+use pxd::math::numerical_methods::TimeScheme;
+use pxd::model::SPMeModel;
+
+// Default model with an implicit scheme, so that the tests are free to choose their time steps
+pub fn model() -> SPMeModel {
+    SPMeModel {
+        time_scheme: TimeScheme::CrankNicolson,
+        ..SPMeModel::default()
+    }
+}
@@ -0,0 +1,71 @@
+use pxd::model::{AdaptiveStepping, Control, SPMeModel, TimeStepping};
+use pxd::{Error, OutputSpecification, Simulate, Solution};
+
+mod common;
+use common::model;
+
+fn charged_model() -> SPMeModel {
+    // Start from a partly charged cell, so that both directions of current are allowed
+    let mut model: SPMeModel = model();
+    let t: Vec<f64> = (0..=60).map(|step| step as f64 * 30.0).collect();
+    model.simulate(&t, &vec![3.35; t.len()]);
+    model
+}
+
+#[test]
+fn voltage_control_round_trips_through_current_control() {
+    let t: Vec<f64> = (1..=120).map(|step| step as f64 * 5.0).collect();
+    let mut held = charged_model();
+    let mut replayed = held.clone();
+    let target: f64 = held.clone().simulate(&[0.0, 1.0], &[0.0, 0.0])[1] + 0.05;
+
+    let control: Vec<Control> = vec![Control::Voltage(target); t.len()];
+    let solution: Solution = held.solve_controlled(&t, &control, &OutputSpecification::default()).unwrap();
+    assert!(solution.voltage.iter().all(|v| (v - target).abs() < 1e-6));
+    // Holding a voltage above open circuit charges the cell with a decaying current
+    assert!(solution.current.iter().all(|i| *i > 0.0));
+    assert!(solution.current[t.len() - 1] < solution.current[0]);
+
+    // The solved current reproduces the held voltage under current control
+    let voltage: Vec<f64> = replayed.simulate(&t, &solution.current);
+    for (a, b) in voltage.iter().zip(&solution.voltage) {
+        assert!((a - b).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn power_control_and_mixed_profiles() {
+    let t: Vec<f64> = (1..=90).map(|step| step as f64 * 10.0).collect();
+    let control: Vec<Control> = (0..t.len())
+        .map(|k| match k / 30 {
+            0 => Control::Power(-8.0),
+            1 => Control::Current(0.0),
+            _ => Control::Power(5.0),
+        })
+        .collect();
+    let mut model = charged_model();
+    let solution: Solution = model.solve_controlled(&t, &control, &OutputSpecification::default()).unwrap();
+
+    for (k, control) in control.iter().enumerate() {
+        let power: f64 = solution.voltage[k] * solution.current[k];
+        match control {
+            Control::Power(target) => assert!((power - target).abs() < 1e-6, "{power} vs {target}"),
+            Control::Current(current) => assert_eq!(solution.current[k], *current),
+            Control::Voltage(_) => unreachable!(),
+        }
+    }
+    // The current rises during the constant power discharge as the voltage falls
+    assert!(solution.current[29] < solution.current[0]);
+}
+
+#[test]
+fn adaptive_time_stepping_is_rejected() {
+    let mut model = SPMeModel {
+        time_stepping: TimeStepping::Adaptive(AdaptiveStepping::default()),
+        ..charged_model()
+    };
+    let control: Vec<Control> = vec![Control::Voltage(4.0); 10];
+    let t: Vec<f64> = (0..10).map(|step| step as f64).collect();
+    let result = model.solve_controlled(&t, &control, &OutputSpecification::default());
+    assert!(matches!(result, Err(Error::InvalidParameter { .. })), "{result:?}");
+}
@@ -0,0 +1,147 @@
+use pxd::dfn::DFNModel;
+use pxd::hysteresis::Hysteresis;
+use pxd::model::{ActiveMaterial, Particle, SPMeModel};
+use pxd::ocv::TanhSeries;
+use pxd::sei::{Sei, SeiGrowth};
+use pxd::size_distribution::LognormalDistribution;
+use pxd::thermal::ThermalModel;
+use pxd::{Error, OutputSpecification, Simulate, Solution};
+
+use std::sync::Arc;
+
+mod common;
+use common::model;
+
+fn quasi_static() -> SPMeModel {
+    // The DFN model has no double layers
+    let mut model: SPMeModel = model();
+    model.negative_electrode.double_layer_capacitance = 0.0;
+    model.positive_electrode.double_layer_capacitance = 0.0;
+    model
+}
+
+fn graphite_si_hysteresis() -> Hysteresis {
+    Hysteresis::new(Arc::new(TanhSeries::graphite_si()), Arc::new(TanhSeries::graphite_si()), 100.0)
+}
+
+#[test]
+fn matches_spme_at_low_rate() {
+    let time: Vec<f64> = (0..=180).map(|t| t as f64 * 10.0).collect();
+    let current: Vec<f64> = vec![0.34; time.len()]; // C/10
+    let expected: Vec<f64> = model().simulate(&time, &current);
+    let voltage: Vec<f64> = DFNModel::try_from(quasi_static()).unwrap().simulate(&time, &current);
+    for (v, e) in voltage.iter().zip(&expected) {
+        assert!((v - e).abs() < 5e-3, "{v} vs {e}");
+    }
+}
+
+#[test]
+fn contributions_add_up_and_rest_gives_open_circuit_voltage() {
+    let time: Vec<f64> = (0..=60).map(|t| t as f64 * 10.0).collect();
+    let mut model: DFNModel = DFNModel::try_from(quasi_static()).unwrap();
+    let solution: Solution = model
+        .solve(&time, &vec![0.0; time.len()], &OutputSpecification::all())
+        .unwrap();
+    let ocv: Vec<f64> = solution.open_circuit_voltage();
+    for (voltage, ocv) in solution.voltage.iter().zip(&ocv) {
+        assert!((voltage - ocv).abs() < 1e-9, "{voltage} vs {ocv}");
+    }
+
+    let solution: Solution = model
+        .solve(&time, &vec![3.4; time.len()], &OutputSpecification::default())
+        .unwrap();
+    let ocv: Vec<f64> = solution.open_circuit_voltage();
+    for (k, ocv) in ocv.iter().enumerate() {
+        let voltage: f64 = ocv
+            - solution.negative_electrode.surface_overpotential[k]
+            - solution.positive_electrode.surface_overpotential[k]
+            + solution.electrolyte_concentration_overpotential[k]
+            + solution.solid_ohmic_overpotential[k]
+            + solution.electrolyte_ohmic_overpotential[k]
+            + solution.contact_overpotential[k];
+        assert!((voltage - solution.voltage[k]).abs() < 1e-9);
+        assert!(solution.voltage[k] > *ocv);
+    }
+}
+
+#[test]
+fn reaction_concentrates_near_separator() {
+    // At 1C charge the electrolyte limits the reaction, so the negative particles next to the separator fill first
+    let time: Vec<f64> = (0..=60).map(|t| t as f64 * 5.0).collect();
+    let mut model: DFNModel = DFNModel::try_from(quasi_static()).unwrap();
+    model.simulate(&time, &vec![3.4; time.len()]);
+
+    let stoichiometry: Vec<f64> = model
+        .negative_particles
+        .iter()
+        .map(|particle: &Particle| particle.average_concentration() / particle.concentration_max)
+        .collect();
+    assert!(stoichiometry.windows(2).all(|w| w[0] < w[1]), "{stoichiometry:?}");
+}
+
+#[test]
+fn discretisation_keeps_a_particle_per_node() {
+    let time: Vec<f64> = (0..=30).map(|t| t as f64 * 10.0).collect();
+    let current: Vec<f64> = vec![3.4; time.len()];
+    let mut model: DFNModel = DFNModel::try_from(quasi_static()).unwrap();
+    model.simulate(&time, &current);
+    model.set_discretisation([30, 10, 30]).unwrap();
+    assert_eq!(model.negative_particles.len(), 30);
+    assert_eq!(model.positive_particles.len(), 30);
+    let stoichiometry: Vec<f64> = model
+        .negative_particles
+        .iter()
+        .map(|particle: &Particle| particle.average_concentration() / particle.concentration_max)
+        .collect();
+    assert!(stoichiometry.windows(2).all(|w| w[0] <= w[1]), "{stoichiometry:?}");
+    assert!(model.solve(&time, &current, &OutputSpecification::default()).is_ok());
+
+    // Changing only the electrolyte mesh leaves the particles behind
+    model.electrolyte.set_discretisation([20, 10, 20]).unwrap();
+    let result = model.solve(&time, &current, &OutputSpecification::default());
+    assert!(matches!(result, Err(Error::InvalidParameter { .. })));
+}
+
+#[test]
+fn unsupported_parts_of_the_spme_are_rejected() {
+    let mut thermal: SPMeModel = quasi_static();
+    thermal.thermal = Some(ThermalModel::default());
+    let mut double_layer: SPMeModel = quasi_static();
+    double_layer.positive_electrode.double_layer_capacitance = 0.2;
+    let mut blend: SPMeModel = quasi_static();
+    blend
+        .negative_electrode
+        .set_size_distribution(&LognormalDistribution::new(6.1e-6, 2e-6), 3)
+        .unwrap();
+    let mut sei: SPMeModel = quasi_static();
+    sei.negative_electrode.sei = Some(Sei::new(SeiGrowth::solvent_diffusion_limited()));
+    let mut hysteresis: SPMeModel = quasi_static();
+    hysteresis.negative_electrode.hysteresis = Some(graphite_si_hysteresis());
+    for spme in [thermal, double_layer, blend, sei, hysteresis] {
+        assert!(matches!(DFNModel::try_from(spme), Err(Error::InvalidParameter { .. })));
+    }
+}
+
+#[test]
+fn unsupported_parts_set_after_conversion_are_rejected() {
+    // The electrodes are public, so the checks of the conversion are repeated when the model is run
+    let mut double_layer: DFNModel = DFNModel::default();
+    double_layer.positive_electrode.double_layer_capacitance = 0.2;
+    let mut blend: DFNModel = DFNModel::default();
+    let material: ActiveMaterial = ActiveMaterial {
+        particle: blend.negative_electrode.particle.clone(),
+        open_circuit_potential: blend.negative_electrode.open_circuit_potential.clone(),
+        hysteresis: None,
+        volume_fraction: 0.01,
+        reaction_rate_constant: blend.negative_electrode.reaction_rate_constant,
+    };
+    blend.negative_electrode.blend.push(material);
+    let mut sei: DFNModel = DFNModel::default();
+    sei.negative_electrode.sei = Some(Sei::new(SeiGrowth::solvent_diffusion_limited()));
+    let mut hysteresis: DFNModel = DFNModel::default();
+    hysteresis.negative_electrode.hysteresis = Some(graphite_si_hysteresis());
+    for mut dfn in [double_layer, blend, sei, hysteresis] {
+        let result = dfn.try_simulate(&[0.0, 1.0], &[1.0, 1.0]);
+        assert!(matches!(result, Err(Error::InvalidParameter { parameter: "DFN model", .. })), "{result:?}");
+    }
+}
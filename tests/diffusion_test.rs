@@ -1,4 +1,7 @@
-use pxd::math::numerical_methods::{ftcs_stable, forward_time_centered_space_linear, forward_time_centered_space_radial};
+use pxd::math::numerical_methods::{
+    ftcs_stable, forward_time_centered_space_linear, forward_time_centered_space_radial,
+    crank_nicolson_linear, crank_nicolson_radial, backward_euler_radial, tridiagonal_solve, TimeScheme,
+};
 use std::fs::File;
 use std::io::{Write, BufWriter};
 
@@ -67,4 +70,111 @@ fn test_fcts() {
         assert!(all_less_or_equal, "The concentration of some x-point is below the initial value.");
 
     }
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_tridiagonal_solve() {
+    // [2 1 0; 1 3 1; 0 1 2] x = [4 10 8] has solution x = [1 2 3]
+    let lower = [0.0, 1.0, 1.0];
+    let diag = [2.0, 3.0, 2.0];
+    let upper = [1.0, 1.0, 0.0];
+    let mut rhs = [4.0, 10.0, 8.0];
+    tridiagonal_solve(&lower, &diag, &upper, &mut rhs);
+    for (x, expected) in rhs.iter().zip([1.0, 2.0, 3.0]) {
+        assert!((x - expected).abs() < 1e-12, "Tridiagonal solution {:?} is wrong", rhs);
+    }
+}
+
+#[test]
+fn test_crank_nicolson_linear_matches_ftcs() {
+    // Same problem as test_fcts, but Crank-Nicolson takes a timestep 10x above the FTCS stability limit
+    let nx: usize = 100;
+    let dx: f64 = 1e-6; // meters
+    let diffusion_coeff: f64 = 1e-12;
+    let mut ftcs: Vec<f64> = vec![1000.0; nx];
+    ftcs[0] = 1300.0;
+    let mut cn: Vec<f64> = ftcs.clone();
+
+    let time_span: f64 = 600.0; // seconds
+    let dt_ftcs: f64 = 0.1; // seconds
+    let dt_cn: f64 = 5.0 * dx * dx / diffusion_coeff; // seconds
+    assert!(!ftcs_stable(dt_cn, dx, diffusion_coeff));
+
+    for _ in 0..(time_span / dt_ftcs).round() as usize {
+        forward_time_centered_space_linear(&mut ftcs, dx, dt_ftcs, diffusion_coeff, 0.0);
+    }
+    for _ in 0..(time_span / dt_cn).round() as usize {
+        crank_nicolson_linear(&mut cn, dx, dt_cn, diffusion_coeff, 0.0);
+    }
+
+    // Zero flux boundaries conserve the total amount
+    let total_init: f64 = 1300.0 + 1000.0 * (nx - 1) as f64;
+    assert!((cn.iter().sum::<f64>() - total_init).abs() < 1e-6, "Crank-Nicolson does not conserve mass");
+
+    for (a, b) in ftcs.iter().zip(&cn) {
+        assert!((a - b).abs() < 1.0, "Crank-Nicolson deviates from FTCS: {:?} vs {:?}", a, b);
+    }
+}
+
+#[test]
+fn test_implicit_radial_large_timestep() {
+    // Particle with a constant surface flux, stepped with a 1 s timestep which is far outside the FTCS limit
+    let radius: f64 = 6.1e-6; // meters
+    let n: usize = 20;
+    let dr: f64 = radius / n as f64;
+    let diffusion_coeff: f64 = 5e-14; // m^2/s
+    let flux: f64 = -1e-5; // mol/(s*m^2), into the particle
+    let dt_ftcs: f64 = 1e-3; // seconds
+    let dt_implicit: f64 = 1.0; // seconds
+    assert!(ftcs_stable(dt_ftcs, dr, diffusion_coeff));
+    assert!(!ftcs_stable(dt_implicit, dr, diffusion_coeff));
+
+    let mut ftcs: Vec<f64> = vec![1000.0; n];
+    let mut cn: Vec<f64> = ftcs.clone();
+    let mut be: Vec<f64> = ftcs.clone();
+
+    for _ in 0..10_000 {
+        forward_time_centered_space_radial(&mut ftcs, dr, dt_ftcs, diffusion_coeff, radius, flux);
+    }
+    for _ in 0..10 {
+        crank_nicolson_radial(&mut cn, dr, dt_implicit, diffusion_coeff, radius, flux);
+        backward_euler_radial(&mut be, dr, dt_implicit, diffusion_coeff, radius, flux);
+    }
+
+    for i in 0..n {
+        assert!((ftcs[i] - cn[i]).abs() < 1.0, "Crank-Nicolson deviates from FTCS at node {i}: {:?} vs {:?}", ftcs[i], cn[i]);
+        assert!((ftcs[i] - be[i]).abs() < 2.0, "Backward euler deviates from FTCS at node {i}: {:?} vs {:?}", ftcs[i], be[i]);
+    }
+    // Lithium flows into the particle, so the surface must have increased
+    assert!(cn[n - 1] > 1000.0);
+}
+
+#[test]
+fn test_finite_volume_conserves_mass() {
+    // Three regions with different widths and porosities, like negative electrode, separator and
+    // positive electrode. Equal and opposite sources must leave the total amount unchanged.
+    let mut dx: Vec<f64> = vec![4e-6; 20];
+    dx.extend(vec![1e-6; 10]);
+    dx.extend(vec![3e-6; 20]);
+    let mut porosity: Vec<f64> = vec![0.25; 20];
+    porosity.extend(vec![0.5; 10]);
+    porosity.extend(vec![0.35; 20]);
+    let diffusivity: Vec<f64> = vec![1.7e-10; 50];
+    let mut source: Vec<f64> = vec![-1e-3 / 80e-6; 20];
+    source.extend(vec![0.0; 10]);
+    source.extend(vec![1e-3 / 60e-6; 20]);
+
+    let total = |y: &[f64]| -> f64 { y.iter().zip(&dx).zip(&porosity).map(|((y, dx), e)| y * dx * e).sum() };
+
+    for scheme in [TimeScheme::BackwardEuler, TimeScheme::CrankNicolson] {
+        let mut y: Vec<f64> = vec![1000.0; 50];
+        let total_init: f64 = total(&y);
+        for _ in 0..100 {
+            scheme.step_finite_volume(&mut y, &dx, &porosity, &diffusivity, &source, 1.0);
+        }
+        assert!(((total(&y) - total_init) / total_init).abs() < 1e-12, "{:?} does not conserve mass", scheme);
+        // Depleted where the source is negative and enriched where it is positive
+        assert!(y[0] < 1000.0 && y[49] > 1000.0);
+        assert!(y.windows(2).all(|w| w[0] <= w[1]), "{:?} profile is not monotonic", scheme);
+    }
+}
@@ -0,0 +1,110 @@
+use pxd::math::numerical_methods::TimeScheme;
+use pxd::model::{Region, SPMeModel};
+use pxd::{Error, Simulate};
+
+#[test]
+fn invalid_input_is_reported() {
+    let mut model = SPMeModel::default();
+
+    let result = model.try_simulate(&[0.0, 1.0, 2.0], &[1.0, 1.0]);
+    assert!(matches!(result, Err(Error::MismatchedLengths { time: 3, current: 2 })));
+
+    let result = model.try_simulate(&[], &[]);
+    assert!(matches!(result, Err(Error::EmptyInput)));
+
+    let result = model.try_simulate(&[0.0, 2.0, 1.0], &[1.0, 1.0, 1.0]);
+    assert!(matches!(result, Err(Error::UnsortedTime { index: 2 })));
+}
+
+#[test]
+fn non_finite_input_is_reported() {
+    // A NaN time passes the sorting check and a NaN current reaches the kinetics, so both are rejected up front
+    let mut model = SPMeModel::default();
+    let result = model.try_simulate(&[0.0, f64::NAN, 2.0], &[1.0, 1.0, 1.0]);
+    assert!(matches!(result, Err(Error::NonFiniteInput { index: 1 })), "{result:?}");
+
+    let result = model.try_simulate(&[0.0, 1.0, 2.0], &[1.0, 1.0, f64::NAN]);
+    assert!(matches!(result, Err(Error::NonFiniteInput { index: 2 })), "{result:?}");
+
+    let result = model.try_simulate(&[0.0, f64::INFINITY], &[1.0, 1.0]);
+    assert!(matches!(result, Err(Error::NonFiniteInput { index: 1 })), "{result:?}");
+}
+
+#[test]
+fn unstable_ftcs_timestep_is_reported() {
+    let mut model = SPMeModel::default();
+    let result = model.try_simulate(&[0.0, 10.0], &[1.0, 1.0]);
+    assert!(matches!(result, Err(Error::UnstableScheme { .. })), "{result:?}");
+}
+
+#[test]
+fn overfull_electrode_is_reported() {
+    // Active material and electrolyte cannot take up more than the electrode volume
+    let mut model = SPMeModel::default();
+    let electrode = &model.positive_electrode;
+    assert!(electrode.active_material_volume_fraction + electrode.porosity <= 1.0);
+    model.positive_electrode.porosity = 0.335;
+    let result = model.try_simulate(&[0.0, 1.0], &[1.0, 1.0]);
+    assert!(matches!(result, Err(Error::InvalidParameter { .. })), "{result:?}");
+}
+
+#[test]
+fn depleted_electrolyte_is_reported() {
+    let mut model = SPMeModel {
+        time_scheme: TimeScheme::CrankNicolson,
+        ..SPMeModel::default()
+    };
+    // A 3C charge drains the electrolyte at the negative current collector within half a minute
+    let t: Vec<f64> = (0..=60).map(|step| step as f64).collect();
+    let result = model.try_simulate(&t, &vec![10.0; t.len()]);
+    assert!(
+        matches!(result, Err(Error::ElectrolyteDepleted { region: Region::NegativeElectrode, .. })),
+        "{result:?}"
+    );
+}
+
+#[test]
+fn degenerate_discretisation_is_reported() {
+    let mut model = SPMeModel {
+        time_scheme: TimeScheme::CrankNicolson,
+        ..SPMeModel::default()
+    };
+    for nodes in [0, 1] {
+        let result = model.negative_electrode.particle.set_discretisation(nodes);
+        assert!(matches!(result, Err(Error::InvalidParameter { .. })), "{result:?}");
+        let result = model.electrolyte.set_discretisation([20, nodes, 20]);
+        assert!(matches!(result, Err(Error::InvalidParameter { .. })), "{result:?}");
+    }
+    // The model is left as it was
+    assert_eq!(model.electrolyte.concentration.len(), model.electrolyte.nodes.iter().sum::<usize>());
+    let result = model.try_simulate(&[0.0, 1.0], &[1.0, 1.0]);
+    assert!(result.is_ok(), "{result:?}");
+
+    // A mesh that doesn't match the concentration profile
+    model.electrolyte.nodes = [10, 10, 10];
+    let result = model.try_simulate(&[0.0, 1.0], &[1.0, 1.0]);
+    assert!(matches!(result, Err(Error::InvalidParameter { .. })), "{result:?}");
+    let result = model.electrolyte.set_discretisation([20, 10, 20]);
+    assert!(matches!(result, Err(Error::InvalidParameter { .. })), "{result:?}");
+}
+
+#[test]
+fn overdischarge_is_reported() {
+    // The default cell starts empty, so discharging it drains the negative particle surface
+    let t: Vec<f64> = (0..=600).map(|step| step as f64).collect();
+    let i: Vec<f64> = vec![-10.0; t.len()];
+    let mut model = SPMeModel {
+        time_scheme: TimeScheme::BackwardEuler,
+        ..SPMeModel::default()
+    };
+    match model.try_simulate(&t, &i) {
+        Err(Error::StoichiometryOutOfBounds { region, .. }) => assert_eq!(region, Region::NegativeElectrode),
+        other => panic!("Expected the stoichiometry to leave its bounds, got {other:?}"),
+    }
+}
+
+#[test]
+#[should_panic(expected = "Time vector must be sorted")]
+fn simulate_panics_on_invalid_input() {
+    SPMeModel::default().simulate(&[1.0, 0.0], &[1.0, 1.0]);
+}
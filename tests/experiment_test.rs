@@ -0,0 +1,129 @@
+use pxd::experiment::{Experiment, Step, Termination};
+use pxd::model::{AdaptiveStepping, SPMeModel, TimeStepping};
+use pxd::{Error, OutputSpecification};
+
+mod common;
+use common::model;
+
+#[test]
+fn constant_current_constant_voltage_charge() {
+    // Charge at 1C to 4.2 V, hold 4.2 V until C/20 and rest for an hour
+    let experiment = Experiment::new(vec![
+        Step::constant_current(3.35)
+            .until(Termination::VoltageAbove(4.2))
+            .until(Termination::Capacity(3.35))
+            .with_timestep(10.0),
+        Step::constant_voltage(4.2)
+            .until(Termination::CurrentMagnitudeBelow(3.35 / 20.0))
+            .until(Termination::Duration(3.0 * 3600.0))
+            .with_timestep(10.0),
+        Step::rest().until(Termination::Duration(3600.0)).with_timestep(60.0),
+    ]);
+    let mut model: SPMeModel = model();
+    let result = experiment.run(&mut model, &OutputSpecification::default()).unwrap();
+    let solution = &result.solution;
+
+    let in_step = |step: usize| -> Vec<usize> { (1..solution.len()).filter(|&k| result.step[k] == step).collect() };
+    let cc_end: usize = *in_step(0).last().unwrap();
+    let cv: Vec<usize> = in_step(1);
+    let rest: Vec<usize> = in_step(2);
+
+    // The constant current step ends on the voltage limit, which is then held
+    assert!((solution.voltage[cc_end] - 4.2).abs() < 1e-3, "{}", solution.voltage[cc_end]);
+    for &k in &cv {
+        assert!((solution.voltage[k] - 4.2).abs() < 1e-6, "{}", solution.voltage[k]);
+    }
+    assert!(cv.windows(2).all(|w| solution.current[w[1]] < solution.current[w[0]]));
+    let cv_end: usize = *cv.last().unwrap();
+    assert!((solution.current[cv_end] - 3.35 / 20.0).abs() < 5e-3, "{}", solution.current[cv_end]);
+
+    // The rest relaxes the voltage and lasts exactly an hour
+    let rest_end: usize = *rest.last().unwrap();
+    assert!((solution.time[rest_end] - solution.time[cv_end] - 3600.0).abs() < 1e-9);
+    assert!(solution.voltage[rest_end] < 4.2);
+    assert!(rest.iter().all(|&k| solution.current[k] == 0.0));
+}
+
+#[test]
+fn constant_power_discharge() {
+    let experiment = Experiment::new(vec![
+        Step::constant_current(3.35).until(Termination::Capacity(1.0)).with_timestep(10.0),
+        Step::constant_power(-6.0)
+            .until(Termination::VoltageBelow(3.5))
+            .until(Termination::Capacity(1.0))
+            .with_timestep(10.0),
+    ]);
+    let mut model: SPMeModel = model();
+    let result = experiment.run(&mut model, &OutputSpecification::default()).unwrap();
+    let solution = &result.solution;
+
+    let discharge: Vec<usize> = (1..solution.len()).filter(|&k| result.step[k] == 1).collect();
+    assert!(!discharge.is_empty());
+    for &k in &discharge {
+        assert!((solution.voltage[k] * solution.current[k] + 6.0).abs() < 1e-6);
+    }
+    let last: usize = *discharge.last().unwrap();
+    assert!((solution.voltage[last] - 3.5).abs() < 1e-3, "{}", solution.voltage[last]);
+}
+
+#[test]
+fn cycling_with_capacity_and_drive_cycle_steps() {
+    let time: Vec<f64> = (0..=60).map(|step| step as f64).collect();
+    let current: Vec<f64> = time.iter().map(|t| if (t % 20.0) < 10.0 { 2.0 } else { -2.0 }).collect();
+    let experiment = Experiment::new(vec![
+        Step::constant_current(3.35).until(Termination::Capacity(0.5)).with_timestep(30.0),
+        Step::drive_cycle(time, current),
+        Step::constant_current(-3.35).until(Termination::Capacity(0.25)).with_timestep(30.0),
+    ])
+    .repeat(3);
+    let mut model: SPMeModel = model();
+    let result = experiment.run(&mut model, &OutputSpecification::default()).unwrap();
+    let solution = &result.solution;
+
+    assert_eq!(result.cycle[solution.len() - 1], 2);
+    for cycle in 0..3 {
+        for (step, capacity) in [(0, 0.5), (2, 0.25)] {
+            let samples: Vec<usize> = (1..solution.len())
+                .filter(|&k| result.cycle[k] == cycle && result.step[k] == step)
+                .collect();
+            let start: f64 = solution.time[samples[0] - 1];
+            let end: f64 = solution.time[*samples.last().unwrap()];
+            assert!(((end - start) * 3.35 / 3600.0 - capacity).abs() < 1e-9);
+        }
+        let drive: usize = (1..solution.len()).filter(|&k| result.cycle[k] == cycle && result.step[k] == 1).count();
+        assert_eq!(drive, 60);
+    }
+}
+
+#[test]
+fn steps_without_termination_are_rejected() {
+    let experiment = Experiment::new(vec![Step::rest().with_timestep(60.0)]);
+    let result = experiment.run(&mut model(), &OutputSpecification::default());
+    assert!(matches!(result, Err(Error::InvalidExperiment { step: 0, .. })));
+}
+
+#[test]
+fn steps_that_may_never_terminate_are_rejected() {
+    // A voltage limit the cell never reaches, or a capacity limit without current, would run forever
+    for step in [
+        Step::constant_current(3.35).until(Termination::VoltageAbove(5.0)),
+        Step::rest().until(Termination::VoltageBelow(3.0)),
+        Step::rest().until(Termination::Capacity(1.0)),
+        Step::constant_voltage(4.2).until(Termination::Capacity(1.0)),
+    ] {
+        let experiment = Experiment::new(vec![Step::rest().until(Termination::Duration(60.0)), step]);
+        let result = experiment.run(&mut model(), &OutputSpecification::default());
+        assert!(matches!(result, Err(Error::InvalidExperiment { step: 1, .. })), "{result:?}");
+    }
+}
+
+#[test]
+fn adaptive_time_stepping_is_rejected() {
+    let experiment = Experiment::new(vec![Step::rest().until(Termination::Duration(60.0)).with_timestep(10.0)]);
+    let mut model: SPMeModel = SPMeModel {
+        time_stepping: TimeStepping::Adaptive(AdaptiveStepping::default()),
+        ..model()
+    };
+    let result = experiment.run(&mut model, &OutputSpecification::default());
+    assert!(matches!(result, Err(Error::InvalidParameter { parameter: "time stepping", .. })), "{result:?}");
+}
@@ -0,0 +1,62 @@
+use pxd::fitting::{fit_tanh_series, OcvFit};
+use pxd::ocv::{GraphiteSi, Nmc811, OpenCircuitPotential, TanhSeries};
+use pxd::Error;
+
+fn perturbed(series: &TanhSeries, factor: f64) -> TanhSeries {
+    // Scales every coefficient by alternating factors, to start the fit away from the solution
+    let coefficients: Vec<f64> = series
+        .coefficients()
+        .iter()
+        .enumerate()
+        .map(|(i, c)| if i % 2 == 0 { c * (1.0 + factor) } else { c * (1.0 - factor) })
+        .collect();
+    TanhSeries::from_coefficients(&coefficients).unwrap()
+}
+
+#[test]
+fn tanh_series_reproduces_mj1_fits() {
+    let (graphite, nmc) = (TanhSeries::graphite_si(), TanhSeries::nmc811());
+    for i in 0..=100 {
+        let x: f64 = i as f64 / 100.0;
+        assert!((graphite.potential(x) - GraphiteSi.potential(x)).abs() < 1e-12);
+        assert!((nmc.potential(x) - Nmc811.potential(x)).abs() < 1e-12);
+        let h: f64 = 1e-6;
+        let difference: f64 = (graphite.potential(x + h) - graphite.potential(x - h)) / (2.0 * h);
+        assert!((graphite.derivative(x) - difference).abs() < 1e-4 * difference.abs().max(1.0));
+    }
+    assert_eq!(TanhSeries::from_coefficients(&graphite.coefficients()).unwrap(), graphite);
+    for length in [0, 3, 5, 8] {
+        let result = TanhSeries::from_coefficients(&vec![1.0; length]);
+        assert!(matches!(result, Err(Error::InvalidParameter { .. })), "{length}: {result:?}");
+    }
+}
+
+#[test]
+fn fit_recovers_graphite_si_from_noisy_data() {
+    // Measured data is emulated by the MJ1 fit with a deterministic 1 mV ripple
+    let x: Vec<f64> = (0..=200).map(|i| 0.01 + 0.98 * i as f64 / 200.0).collect();
+    let u: Vec<f64> = x.iter().enumerate().map(|(i, x)| GraphiteSi.potential(*x) + 1e-3 * (i as f64 * 1.7).sin()).collect();
+
+    let fit: OcvFit = fit_tanh_series(&x, &u, &perturbed(&TanhSeries::graphite_si(), 0.05)).unwrap();
+    assert_eq!(fit.coefficients.len(), 4 + 3 * 3);
+    assert_eq!(fit.coefficients[3], 0.0); // no linear term in the graphite form
+    assert_eq!(fit.residuals.len(), x.len());
+    assert!(fit.rms_error < 1e-3, "{}", fit.rms_error);
+    assert!(fit.converged && fit.iterations > 0 && fit.iterations < 500, "{}", fit.iterations);
+    for x in x.iter().step_by(10) {
+        assert!((fit.series.potential(*x) - GraphiteSi.potential(*x)).abs() < 2e-3);
+    }
+}
+
+#[test]
+fn fit_linear_plus_tanh_form() {
+    let x: Vec<f64> = (0..=100).map(|i| 0.2 + 0.79 * i as f64 / 100.0).collect();
+    let u: Vec<f64> = x.iter().map(|x| Nmc811.potential(*x)).collect();
+
+    let fit: OcvFit = fit_tanh_series(&x, &u, &perturbed(&TanhSeries::nmc811(), 0.03)).unwrap();
+    assert_eq!(&fit.coefficients[..2], &[0.0, 0.0]); // no exponential in the NMC form
+    assert!(fit.rms_error < 1e-4, "{}", fit.rms_error);
+
+    let too_few = fit_tanh_series(&x[..5], &u[..5], &TanhSeries::nmc811());
+    assert!(matches!(too_few, Err(Error::InvalidTable(_))));
+}
@@ -1,4 +1,5 @@
-use pxd::model::SPMeModel;
+use pxd::math::numerical_methods::TimeScheme;
+use pxd::model::{AdaptiveStepping, Region, SPMeModel, TimeStepping};
 use pxd::Simulate;
 
 use std::fs::File;
@@ -40,4 +41,236 @@ fn simulate_default_cycle() {
     let cell_potential = model.simulate(&t, &i);
     println!("{:?}", cell_potential);
     save_vec_to_file(&cell_potential, "cell_voltage.csv").unwrap();
-}
\ No newline at end of file
+}
+
+#[test]
+fn simulate_default_cycle_crank_nicolson() {
+    // Same cycle as simulate_default_cycle, but with 1 s timesteps which FTCS cannot handle
+    let time: f64 = 60.0 * 60.0; // seconds
+    let dt: f64 = 1.0; // seconds
+    let current: f64 = 3.2; // Amperes
+    let n_steps: usize = (2.0 * time / dt).ceil() as usize;
+    let t: Vec<f64> = (0..n_steps).map(|step| step as f64 * dt).collect();
+    let i: Vec<f64> = t.iter().map(|&time_val| if time_val < time { current } else { -current }).collect();
+
+    let mut model = SPMeModel {
+        time_scheme: TimeScheme::CrankNicolson,
+        ..SPMeModel::default()
+    };
+
+    let cell_potential = model.simulate(&t, &i);
+    assert!(
+        cell_potential.iter().all(|v| v.is_finite() && (2.5..4.5).contains(v)),
+        "Cell potential out of range with Crank-Nicolson"
+    );
+}
+
+#[test]
+fn simulate_adaptive_uneven_grid() {
+    // 10 minute 1C charge followed by a 20 minute rest, sampled unevenly like a cycler log
+    // Sampling starts dense at each current edge and grows sparser
+    let mut t: Vec<f64> = vec![0.0];
+    for (start, end) in [(0.0, 600.0), (600.0, 1800.0)] {
+        while *t.last().unwrap() < end {
+            let last: f64 = *t.last().unwrap();
+            t.push((last + (0.05 + 0.02 * (last - start)).min(10.0)).min(end));
+        }
+    }
+    let i: Vec<f64> = t.iter().map(|&time_val| if time_val <= 600.0 { 3.2 } else { 0.0 }).collect();
+
+    let mut adaptive = SPMeModel {
+        time_scheme: TimeScheme::CrankNicolson,
+        time_stepping: TimeStepping::Adaptive(AdaptiveStepping::default()),
+        ..SPMeModel::default()
+    };
+    let cell_potential = adaptive.simulate(&t, &i);
+
+    // Reference with small fixed steps on a uniform grid
+    let dt: f64 = 0.05;
+    let t_ref: Vec<f64> = (0..=(1800.0 / dt) as usize).map(|step| step as f64 * dt).collect();
+    let i_ref: Vec<f64> = t_ref.iter().map(|&time_val| if time_val <= 600.0 { 3.2 } else { 0.0 }).collect();
+    let mut reference = SPMeModel {
+        time_scheme: TimeScheme::CrankNicolson,
+        ..SPMeModel::default()
+    };
+    let reference_potential = reference.simulate(&t_ref, &i_ref);
+
+    // Both steppers take a step at the first current before the first sample
+    for (time_val, v) in t.iter().zip(&cell_potential) {
+        if *time_val > 1800.0 {
+            break;
+        }
+        // Linear interpolation of the reference onto the uneven grid
+        let j: usize = ((time_val / dt).floor() as usize).min(t_ref.len() - 2);
+        let w: f64 = (time_val - t_ref[j]) / dt;
+        let v_ref: f64 = reference_potential[j] + w * (reference_potential[j + 1] - reference_potential[j]);
+        assert!(
+            (v - v_ref).abs() < 5e-3,
+            "Adaptive solution deviates at t = {time_val}: {v} vs {v_ref}"
+        );
+    }
+}
+
+#[test]
+fn adaptive_retries_shortened_last_step() {
+    // The only step of the segment is shortened to 0.3 s and rejected, and has to be retried with a shorter one
+    let mut model = SPMeModel {
+        time_scheme: TimeScheme::CrankNicolson,
+        time_stepping: TimeStepping::Adaptive(AdaptiveStepping {
+            initial_timestep: 1.0,
+            ..AdaptiveStepping::default()
+        }),
+        ..SPMeModel::default()
+    };
+    let voltage: Vec<f64> = model.try_simulate(&[0.0, 0.3], &[5.0, 5.0]).unwrap();
+    assert!(voltage.iter().all(|v| v.is_finite()));
+}
+
+#[test]
+fn bruggeman_correction_increases_polarisation() {
+    // Tortuous pores slow down electrolyte diffusion, so the charging voltage must increase with the exponent
+    let t: Vec<f64> = (0..600).map(|step| step as f64).collect();
+    let i: Vec<f64> = vec![3.2; t.len()];
+
+    let mut potentials: Vec<f64> = Vec::new();
+    for bruggeman_exponent in [0.0, 1.0, 1.5] {
+        let mut model = SPMeModel {
+            time_scheme: TimeScheme::CrankNicolson,
+            ..SPMeModel::default()
+        };
+        model.negative_electrode.bruggeman_exponent = bruggeman_exponent;
+        model.positive_electrode.bruggeman_exponent = bruggeman_exponent;
+        model.separator.bruggeman_exponent = bruggeman_exponent;
+        potentials.push(*model.simulate(&t, &i).last().unwrap());
+    }
+    assert!(
+        potentials.windows(2).all(|w| w[0] < w[1]),
+        "Charging potential does not increase with the Bruggeman exponent: {:?}",
+        potentials
+    );
+}
+
+#[test]
+fn double_layer_smooths_pulse_response() {
+    // First 20 ms of a 1C charge pulse from rest. The double layer has to charge before the reaction
+    // carries the current, so the voltage rises gradually instead of jumping.
+    let dt: f64 = 1e-4; // seconds
+    let t: Vec<f64> = (1..=200).map(|step| step as f64 * dt).collect();
+    let i: Vec<f64> = vec![3.2; t.len()];
+
+    let mut rest = SPMeModel {
+        time_scheme: TimeScheme::CrankNicolson,
+        ..SPMeModel::default()
+    };
+    let open_circuit = rest.simulate(&[0.0, 1.0], &[0.0, 0.0])[1];
+
+    let mut with_double_layer = SPMeModel {
+        time_scheme: TimeScheme::CrankNicolson,
+        ..SPMeModel::default()
+    };
+    let response = with_double_layer.simulate(&t, &i);
+
+    let mut without_double_layer = SPMeModel {
+        time_scheme: TimeScheme::CrankNicolson,
+        ..SPMeModel::default()
+    };
+    without_double_layer.negative_electrode.double_layer_capacitance = 0.0;
+    without_double_layer.positive_electrode.double_layer_capacitance = 0.0;
+    let instant = without_double_layer.simulate(&t, &i);
+
+    // The ohmic drop is instantaneous in both cases, only the kinetic overpotential is delayed
+    let final_jump: f64 = response[199] - open_circuit;
+    assert!(final_jump > 0.0);
+    assert!(response[199] - response[0] > 0.2 * final_jump, "Double layer does not delay the voltage response");
+    assert!(response.windows(2).all(|w| w[0] <= w[1]), "Voltage is not rising monotonically");
+    assert!(instant[199] - instant[0] < 0.1 * (instant[199] - open_circuit), "Quasi-static kinetics should jump instantly");
+    assert!((response[199] - instant[199]).abs() < 0.1 * final_jump, "Double layer has not settled after 20 ms");
+}
+
+#[test]
+fn single_sample_is_taken_without_a_step() {
+    // One sample has no spacing to step over, so it shows the initial state under the applied current, as with
+    // adaptive time stepping
+    for current in [0.0, 1.0] {
+        let fixed: Vec<f64> = SPMeModel::default().try_simulate(&[0.0], &[current]).unwrap();
+        let adaptive: Vec<f64> = SPMeModel {
+            time_stepping: TimeStepping::Adaptive(AdaptiveStepping::default()),
+            ..SPMeModel::default()
+        }
+        .try_simulate(&[0.0], &[current])
+        .unwrap();
+        assert!(fixed[0].is_finite());
+        assert!((fixed[0] - adaptive[0]).abs() < 1e-12, "{} vs {}", fixed[0], adaptive[0]);
+    }
+}
+
+#[test]
+fn contact_resistance_adds_ohmic_drop() {
+    // The contact resistance is a pure series resistance, so it shifts the voltage by I*R at any time
+    let t: Vec<f64> = (0..60).map(|step| step as f64).collect();
+    let i: Vec<f64> = vec![3.2; t.len()];
+
+    let mut without_contact = SPMeModel {
+        time_scheme: TimeScheme::CrankNicolson,
+        contact_resistance: 0.0,
+        ..SPMeModel::default()
+    };
+    let mut with_contact = SPMeModel {
+        contact_resistance: 0.05,
+        ..without_contact.clone()
+    };
+    let reference = without_contact.simulate(&t, &i);
+    let shifted = with_contact.simulate(&t, &i);
+
+    for (a, b) in reference.iter().zip(&shifted) {
+        assert!((b - a - 3.2 * 0.05).abs() < 1e-9, "Contact resistance drop is not I*R: {a} vs {b}");
+    }
+}
+
+#[test]
+fn electrolyte_region_averages_follow_reaction() {
+    // Charging consumes lithium ions in the negative electrode and releases them in the positive electrode.
+    // The region averages are what the exchange current densities see. At 2C the electrolyte at the negative
+    // current collector is depleted within a minute, so the cell is charged at 1C.
+    let t: Vec<f64> = (0..120).map(|step| step as f64).collect();
+    let i: Vec<f64> = vec![3.2; t.len()];
+    let mut model = SPMeModel {
+        time_scheme: TimeScheme::CrankNicolson,
+        ..SPMeModel::default()
+    };
+    model.simulate(&t, &i);
+
+    let c_n: f64 = model.electrolyte_average_concentration(Region::NegativeElectrode);
+    let c_s: f64 = model.electrolyte_average_concentration(Region::Separator);
+    let c_p: f64 = model.electrolyte_average_concentration(Region::PositiveElectrode);
+    assert!(c_n < c_s && c_s < c_p, "Unexpected electrolyte profile: {c_n}, {c_s}, {c_p}");
+    assert!(c_n < 1000.0 && c_p > 1000.0);
+}
+
+#[test]
+fn mesh_refinement_converges() {
+    // Refining the particle and electrolyte meshes should change the voltage by successively smaller amounts
+    let t: Vec<f64> = (0..=120).map(|step| step as f64 * 5.0).collect();
+    let i: Vec<f64> = vec![3.2; t.len()];
+
+    let voltages: Vec<Vec<f64>> = [(10, [10, 5, 10]), (20, [20, 10, 20]), (40, [40, 20, 40])]
+        .into_iter()
+        .map(|(particle_nodes, electrolyte_nodes)| {
+            let mut model = SPMeModel {
+                time_scheme: TimeScheme::CrankNicolson,
+                ..SPMeModel::default()
+            };
+            model.negative_electrode.particle.set_discretisation(particle_nodes).unwrap();
+            model.positive_electrode.particle.set_discretisation(particle_nodes).unwrap();
+            model.electrolyte.set_discretisation(electrolyte_nodes).unwrap();
+            assert_eq!(model.electrolyte.concentration.len(), electrolyte_nodes.iter().sum::<usize>());
+            model.simulate(&t, &i)
+        })
+        .collect();
+
+    let difference = |a: &Vec<f64>, b: &Vec<f64>| a.iter().zip(b).map(|(x, y)| (x - y).abs()).fold(0.0, f64::max);
+    let coarse: f64 = difference(&voltages[0], &voltages[1]);
+    let fine: f64 = difference(&voltages[1], &voltages[2]);
+    assert!(voltages.iter().flatten().all(|v| v.is_finite()));
+    assert!(fine < coarse, "Mesh refinement does not converge: {coarse} then {fine}");
+}
@@ -0,0 +1,71 @@
+use pxd::hysteresis::Hysteresis;
+use pxd::model::SPMeModel;
+use pxd::ocv::{OpenCircuitPotential, TanhSeries};
+use pxd::Simulate;
+
+use std::sync::Arc;
+
+mod common;
+use common::model;
+
+fn silicon_graphite_branches(gap: f64) -> (Arc<dyn OpenCircuitPotential>, Arc<dyn OpenCircuitPotential>) {
+    // Lithiation and delithiation branches split symmetrically around the default curve
+    let mut lithiation: TanhSeries = TanhSeries::graphite_si();
+    let mut delithiation: TanhSeries = TanhSeries::graphite_si();
+    lithiation.offset -= 0.5 * gap;
+    delithiation.offset += 0.5 * gap;
+    (Arc::new(lithiation), Arc::new(delithiation))
+}
+
+#[test]
+fn state_moves_towards_branch_of_current_direction() {
+    let (lithiation, delithiation) = silicon_graphite_branches(0.02);
+    let mut hysteresis: Hysteresis = Hysteresis::new(lithiation.clone(), delithiation.clone(), 50.0);
+    assert!((hysteresis.potential_at_temperature(0.5, 298.15) - TanhSeries::graphite_si().potential(0.5)).abs() < 1e-12);
+
+    // A full lithiation ends on the lithiation branch
+    hysteresis.update(0.5);
+    assert!((hysteresis.state + 1.0).abs() < 1e-9);
+    assert!((hysteresis.potential_at_temperature(0.5, 298.15) - lithiation.potential(0.5)).abs() < 1e-9);
+
+    // A partial delithiation leaves the state between the branches, independent of how it is split up
+    let mut split: Hysteresis = hysteresis.clone();
+    hysteresis.update(-0.01);
+    for _ in 0..10 {
+        split.update(-0.001);
+    }
+    let expected: f64 = 1.0 - 2.0 * (-50.0 * 0.01_f64).exp();
+    assert!((hysteresis.state - expected).abs() < 1e-9);
+    assert!((split.state - expected).abs() < 1e-9);
+
+    // Rest does not change the state
+    hysteresis.update(0.0);
+    assert!((hysteresis.state - expected).abs() < 1e-9);
+}
+
+#[test]
+fn relaxed_voltage_depends_on_previous_direction() {
+    // After a charge the negative electrode rests on its lower lithiation branch, which raises the cell voltage,
+    // and after a partial discharge on its upper delithiation branch
+    let gap: f64 = 0.02;
+    let (lithiation, delithiation) = silicon_graphite_branches(gap);
+    let time: Vec<f64> = (0..=2400).map(|t| t as f64).collect();
+    let current: Vec<f64> = time
+        .iter()
+        .map(|t| match *t {
+            t if t <= 600.0 => 3.0,
+            t if t > 1200.0 && t <= 1800.0 => -1.0,
+            _ => 0.0,
+        })
+        .collect();
+    let mut reference: SPMeModel = model();
+    let mut model: SPMeModel = reference.clone();
+    model.negative_electrode.hysteresis = Some(Hysteresis::new(lithiation, delithiation, 100.0));
+
+    let reference_voltage: Vec<f64> = reference.simulate(&time, &current);
+    let voltage: Vec<f64> = model.simulate(&time, &current);
+    assert!((voltage[1200] - reference_voltage[1200] - 0.5 * gap).abs() < 1e-3);
+    assert!((voltage[2400] - reference_voltage[2400] + 0.5 * gap).abs() < 1e-3);
+    let state: f64 = model.negative_electrode.hysteresis.as_ref().unwrap().state;
+    assert!(state > 0.95 && state < 1.0, "{state}");
+}
@@ -0,0 +1,122 @@
+use pxd::kinetics::KineticLaw;
+use pxd::math::numerical_methods::TimeScheme;
+use pxd::model::SPMeModel;
+use pxd::{Error, OutputSpecification, Simulate, Solution};
+
+const TEMPERATURE: f64 = 298.15; // Kelvin
+const THERMAL_VOLTAGE: f64 = 8.31446261815324 * TEMPERATURE / 96485.33212331001; // V
+
+#[test]
+fn symmetric_butler_volmer_matches_arcsinh() {
+    let kinetics = KineticLaw::default();
+    let i0: f64 = 2.0; // A/m^2
+    for current_density in [-50.0, -1.0, 0.0, 0.3, 20.0] {
+        let expected: f64 = 2.0 * THERMAL_VOLTAGE * (current_density / (2.0 * i0)).asinh();
+        let eta: f64 = kinetics.overpotential(i0, current_density, TEMPERATURE).unwrap();
+        assert!((eta - expected).abs() < 1e-9, "Symmetric butler volmer: {eta} vs {expected}");
+    }
+}
+
+#[test]
+fn asymmetric_butler_volmer_round_trip() {
+    let kinetics = KineticLaw::ButlerVolmer {
+        anodic_transfer_coefficient: 0.3,
+        cathodic_transfer_coefficient: 0.7,
+    };
+    let i0: f64 = 0.5; // A/m^2
+    for current_density in [-100.0, -2.0, -1e-3, 1e-3, 2.0, 100.0] {
+        let eta: f64 = kinetics.overpotential(i0, current_density, TEMPERATURE).unwrap();
+        let (current, _) = kinetics.current_density(i0, eta, TEMPERATURE);
+        assert!(((current - current_density) / current_density).abs() < 1e-9, "Round trip failed at {current_density}");
+    }
+    // A smaller anodic transfer coefficient needs a larger anodic overpotential for the same current
+    let anodic: f64 = kinetics.overpotential(i0, 10.0, TEMPERATURE).unwrap();
+    let cathodic: f64 = kinetics.overpotential(i0, -10.0, TEMPERATURE).unwrap();
+    assert!(anodic > -cathodic);
+}
+
+#[test]
+fn marcus_hush_chidsey_kinetics() {
+    let mhc = KineticLaw::MarcusHushChidsey { reorganisation_energy: 0.2 };
+    let bv = KineticLaw::default();
+    let i0: f64 = 1.0; // A/m^2
+
+    // Same linear response as butler volmer close to equilibrium
+    let (_, slope_mhc) = mhc.current_density(i0, 0.0, TEMPERATURE);
+    let (_, slope_bv) = bv.current_density(i0, 0.0, TEMPERATURE);
+    assert!((slope_mhc - slope_bv).abs() / slope_bv < 1e-6);
+
+    // Analytic derivative matches finite difference
+    let eta: f64 = 0.1; // V
+    let (i_plus, _) = mhc.current_density(i0, eta + 1e-7, TEMPERATURE);
+    let (i_minus, _) = mhc.current_density(i0, eta - 1e-7, TEMPERATURE);
+    let (_, derivative) = mhc.current_density(i0, eta, TEMPERATURE);
+    assert!(((i_plus - i_minus) / 2e-7 - derivative).abs() / derivative < 1e-5);
+
+    // Curved Tafel plot, the current falls far below butler volmer at large overpotentials
+    let (i_mhc, _) = mhc.current_density(i0, 0.5, TEMPERATURE);
+    let (i_bv, _) = bv.current_density(i0, 0.5, TEMPERATURE);
+    assert!(i_mhc < 0.01 * i_bv);
+
+    // Round trip below the limiting current
+    for current_density in [-5.0, -0.1, 0.1, 5.0] {
+        let eta: f64 = mhc.overpotential(i0, current_density, TEMPERATURE).unwrap();
+        let (current, _) = mhc.current_density(i0, eta, TEMPERATURE);
+        assert!(((current - current_density) / current_density).abs() < 1e-9);
+    }
+
+    // No overpotential carries a current beyond the limiting current of about 29 i0
+    for current_density in [-100.0, 100.0] {
+        let result = mhc.overpotential(i0, current_density, TEMPERATURE);
+        assert!(matches!(result, Err(Error::LimitingCurrentExceeded { .. })), "{result:?}");
+    }
+}
+
+#[test]
+fn simulate_with_per_electrode_kinetics() {
+    let t: Vec<f64> = (0..300).map(|step| step as f64).collect();
+    let i: Vec<f64> = vec![3.2; t.len()];
+
+    let mut symmetric = SPMeModel {
+        time_scheme: TimeScheme::CrankNicolson,
+        ..SPMeModel::default()
+    };
+    let mut marcus = symmetric.clone();
+    marcus.negative_electrode.kinetics = KineticLaw::MarcusHushChidsey { reorganisation_energy: 0.2 };
+    let mut asymmetric = symmetric.clone();
+    asymmetric.positive_electrode.kinetics = KineticLaw::ButlerVolmer {
+        anodic_transfer_coefficient: 0.4,
+        cathodic_transfer_coefficient: 0.6,
+    };
+
+    let v_symmetric = symmetric.simulate(&t, &i);
+    let v_marcus = marcus.simulate(&t, &i);
+    let v_asymmetric = asymmetric.simulate(&t, &i);
+    assert!(v_marcus.iter().chain(&v_asymmetric).all(|v| v.is_finite()));
+    // Marcus-Hush-Chidsey needs a larger overpotential than butler volmer for the same current
+    assert!(v_marcus[299] > v_symmetric[299]);
+}
+
+#[test]
+fn electrolyte_depletion_raises_kinetic_overpotential() {
+    // Charging drains the electrolyte in the negative electrode, where the lower salt concentration lowers the
+    // exchange current density. Fast electrolyte diffusion keeps the concentration uniform for reference.
+    let t: Vec<f64> = (0..=120).map(|step| step as f64).collect();
+    let i: Vec<f64> = vec![3.4; t.len()]; // 1C charge
+    let mut depleting = SPMeModel {
+        time_scheme: TimeScheme::CrankNicolson,
+        ..SPMeModel::default()
+    };
+    let mut uniform = depleting.clone();
+    uniform.electrolyte.diffusion_coeff *= 1e3;
+
+    let output = OutputSpecification::default();
+    let depleted: Solution = depleting.solve(&t, &i, &output).unwrap();
+    let reference: Solution = uniform.solve(&t, &i, &output).unwrap();
+    let n: usize = t.len() - 1;
+    let c_e = |model: &SPMeModel| model.electrolyte.concentration[0];
+    assert!(c_e(&depleting) < 0.9 * c_e(&uniform), "{} vs {}", c_e(&depleting), c_e(&uniform));
+    let eta: f64 = depleted.negative_electrode.surface_overpotential[n].abs();
+    let eta_reference: f64 = reference.negative_electrode.surface_overpotential[n].abs();
+    assert!(eta > eta_reference, "{eta} vs {eta_reference}");
+}
@@ -0,0 +1,68 @@
+use pxd::model::{AdaptiveStepping, SPMeModel, TimeStepping};
+use pxd::observer::{CsvObserver, Decimate, EveryNSeconds, MemoryObserver};
+use pxd::{OutputSpecification, Simulate, Solution};
+
+use std::num::NonZeroUsize;
+
+mod common;
+use common::model;
+
+fn charge() -> (Vec<f64>, Vec<f64>) {
+    let t: Vec<f64> = (0..=100).map(|step| step as f64).collect();
+    let i: Vec<f64> = vec![3.2; t.len()];
+    (t, i)
+}
+
+#[test]
+fn memory_observer_sees_every_fixed_step() {
+    let (t, i) = charge();
+    let mut observer = MemoryObserver::new();
+    let solution: Solution = model()
+        .solve_observed(&t, &i, &OutputSpecification::default(), &mut observer)
+        .unwrap();
+
+    assert_eq!(observer.time, t);
+    assert_eq!(observer.voltage, solution.voltage);
+    assert_eq!(observer.electrolyte_concentration[0].len(), 50);
+    assert_eq!(observer.negative_particle_concentration[0].len(), 20);
+}
+
+#[test]
+fn thinning_observers() {
+    let (t, i) = charge();
+
+    let mut every = EveryNSeconds::new(MemoryObserver::new(), 10.0);
+    let mut adaptive = SPMeModel {
+        time_stepping: TimeStepping::Adaptive(AdaptiveStepping::default()),
+        ..model()
+    };
+    adaptive
+        .solve_observed(&t, &i, &OutputSpecification::default(), &mut every)
+        .unwrap();
+    let times: &Vec<f64> = &every.observer.time;
+    assert_eq!(times[0], 0.0);
+    assert!(times.windows(2).all(|w| w[1] - w[0] >= 10.0), "{times:?}");
+    assert!(times.len() >= 9 && times.len() <= 11, "{times:?}");
+
+    let mut decimate = Decimate::new(MemoryObserver::new(), NonZeroUsize::new(25).unwrap());
+    model()
+        .solve_observed(&t, &i, &OutputSpecification::default(), &mut decimate)
+        .unwrap();
+    assert_eq!(decimate.observer.time, vec![0.0, 25.0, 50.0, 75.0, 100.0]);
+}
+
+#[test]
+fn csv_observer_writes_header_and_rows() {
+    let (t, i) = charge();
+    let mut observer = CsvObserver::new(Vec::new());
+    model()
+        .solve_observed(&t, &i, &OutputSpecification::default(), &mut observer)
+        .unwrap();
+
+    let csv: String = String::from_utf8(observer.into_inner()).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), t.len() + 1);
+    assert!(lines[0].starts_with("time,current,voltage,electrolyte_concentration_0,"));
+    assert_eq!(lines[1].split(',').count(), 3 + 50);
+    assert!(lines[1].starts_with("0,3.2,"));
+}
@@ -0,0 +1,188 @@
+use pxd::math::numerical_methods::TimeScheme;
+use pxd::model::{Particle, SPMeModel};
+use pxd::ocv::{
+    open_circuit_voltage_graphite_si, open_circuit_voltage_nmc811, Extrapolation, Graphite, GraphiteSi, Interpolation,
+    Lco, Lfp, Lmo, Nmc532, Nmc811, OpenCircuitPotential, TabulatedPotential,
+};
+use pxd::thermal::ThermalModel;
+use pxd::{Error, OutputSpecification, Simulate, Solution};
+
+use std::sync::Arc;
+
+#[test]
+fn free_functions_match_materials() {
+    let particle = Particle::new(5e-6, 1e-14, 30000.0, 12000.0);
+    assert_eq!(open_circuit_voltage_graphite_si(&particle), GraphiteSi.potential(0.4));
+    assert_eq!(open_circuit_voltage_nmc811(&particle), Nmc811.potential(0.4));
+}
+
+#[test]
+fn potentials_fall_with_lithiation() {
+    // (material, valid stoichiometry range, potential range in V)
+    type Range = (f64, f64);
+    let materials: [(&dyn OpenCircuitPotential, Range, Range); 7] = [
+        (&GraphiteSi, (0.05, 0.95), (0.0, 1.0)),
+        (&Graphite, (0.05, 0.95), (0.0, 1.0)),
+        (&Lfp, (0.05, 0.95), (3.1, 3.6)),
+        (&Lco, (0.55, 0.95), (3.7, 4.3)),
+        (&Lmo, (0.25, 0.95), (3.8, 4.3)),
+        (&Nmc532, (0.3, 0.95), (3.5, 4.3)),
+        (&Nmc811, (0.3, 0.95), (3.4, 4.3)),
+    ];
+    for (material, (x_min, x_max), (u_min, u_max)) in materials {
+        let potentials: Vec<f64> = (0..=50)
+            .map(|i| material.potential(x_min + (x_max - x_min) * i as f64 / 50.0))
+            .collect();
+        assert!(potentials.windows(2).all(|w| w[1] < w[0]), "{material:?} is not decreasing: {potentials:?}");
+        assert!(potentials.iter().all(|u| *u > u_min && *u < u_max), "{material:?}: {potentials:?}");
+    }
+}
+
+#[test]
+fn graphite_lfp_cell() {
+    // An LFP cell sits on the flat 3.2-3.4 V plateau for most of a charge
+    let mut model = SPMeModel {
+        time_scheme: TimeScheme::CrankNicolson,
+        ..SPMeModel::default()
+    };
+    model.negative_electrode.open_circuit_potential = Arc::new(Graphite);
+    model.positive_electrode.open_circuit_potential = Arc::new(Lfp);
+    model.positive_electrode.particle = Particle::new(1e-6, 1e-15, 22806.0, 0.9 * 22806.0);
+
+    let t: Vec<f64> = (1..=120).map(|step| step as f64 * 10.0).collect();
+    let voltage: Vec<f64> = model.simulate(&t, &vec![1.0; t.len()]);
+    assert!(voltage[t.len() / 2..].iter().all(|v| *v > 3.1 && *v < 3.5), "{voltage:?}");
+}
+
+#[test]
+fn tabulated_potential_follows_sampled_curve() {
+    // A PCHIP table of a smooth curve reproduces it and its derivative between the points
+    let x: Vec<f64> = (0..=40).map(|i| 0.3 + 0.65 * i as f64 / 40.0).collect();
+    let u: Vec<f64> = x.iter().map(|x| Nmc532.potential(*x)).collect();
+    let table = TabulatedPotential::new(x.clone(), u.clone(), Interpolation::Pchip, Extrapolation::Constant).unwrap();
+    for i in 0..400 {
+        let x: f64 = 0.31 + 0.59 * i as f64 / 400.0;
+        assert!((table.potential(x) - Nmc532.potential(x)).abs() < 1e-4);
+        assert!((table.derivative(x) - Nmc532.derivative(x)).abs() < 2e-2 * Nmc532.derivative(x).abs().max(1.0));
+    }
+
+    // The analytic derivative matches finite differences of the interpolant
+    for x in [0.312, 0.5, 0.777, 0.94] {
+        let h: f64 = 1e-7;
+        let difference: f64 = (table.potential(x + h) - table.potential(x - h)) / (2.0 * h);
+        assert!((table.derivative(x) - difference).abs() < 1e-5);
+    }
+}
+
+#[test]
+fn tabulated_potential_is_monotone_and_extrapolates() {
+    // A plateau with sharp steps, where an ordinary spline would overshoot
+    let x: Vec<f64> = vec![0.0, 0.05, 0.1, 0.5, 0.9, 0.95, 1.0];
+    let u: Vec<f64> = vec![4.0, 3.5, 3.42, 3.4, 3.38, 3.0, 2.5];
+    let pchip = TabulatedPotential::new(x.clone(), u.clone(), Interpolation::Pchip, Extrapolation::Linear).unwrap();
+    let linear = TabulatedPotential::new(x.clone(), u.clone(), Interpolation::Linear, Extrapolation::Constant).unwrap();
+
+    let samples: Vec<f64> = (0..=1000).map(|i| pchip.potential(i as f64 / 1000.0)).collect();
+    assert!(samples.windows(2).all(|w| w[1] <= w[0]));
+    assert!((linear.potential(0.3) - 3.41).abs() < 1e-12);
+    assert!((linear.derivative(0.3) + 0.05).abs() < 1e-12);
+
+    // Constant extrapolation holds the end values, linear extrapolation continues the end slopes
+    assert_eq!(linear.potential(-0.1), 4.0);
+    assert_eq!(linear.derivative(1.2), 0.0);
+    let slope: f64 = pchip.derivative(1.0);
+    assert!(slope < 0.0);
+    assert!((pchip.potential(1.1) - (2.5 + 0.1 * slope)).abs() < 1e-12);
+}
+
+#[test]
+fn tabulated_potential_from_csv() {
+    let path = std::env::temp_dir().join(format!("pxd_ocv_table_{}.csv", std::process::id()));
+    std::fs::write(
+        &path,
+        "# measured at C/50\nstoichiometry,voltage\n1.0, 3.5, 0.1\n0.5, 3.8, 0.1\n\n0.0, 4.2, 0.1\n",
+    )
+    .unwrap();
+    let table = TabulatedPotential::from_csv(&path, Interpolation::Linear, Extrapolation::Constant).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(table.points(), (&[0.0, 0.5, 1.0][..], &[4.2, 3.8, 3.5][..]));
+    assert!((table.potential(0.75) - 3.65).abs() < 1e-12);
+
+    let bad = "x,u\n0.0,4.2\n0.5,oops\n".as_bytes();
+    let result = TabulatedPotential::from_reader(bad, Interpolation::Pchip, Extrapolation::Constant);
+    assert!(matches!(result, Err(Error::InvalidTable(_))));
+    let duplicate = TabulatedPotential::new(vec![0.0, 0.5, 0.5], vec![4.0, 3.8, 3.7], Interpolation::Pchip, Extrapolation::Constant);
+    assert!(matches!(duplicate, Err(Error::InvalidTable(_))));
+}
+
+// Material with a measured entropic coefficient, here a linear profile
+#[derive(Debug)]
+struct Entropic {
+    material: Arc<dyn OpenCircuitPotential>,
+    coefficient: [f64; 2], // V/K, at x = 0 and x = 1
+}
+
+impl OpenCircuitPotential for Entropic {
+    fn potential(&self, stoichiometry: f64) -> f64 {
+        self.material.potential(stoichiometry)
+    }
+
+    fn entropic_coefficient(&self, stoichiometry: f64) -> f64 {
+        self.coefficient[0] + stoichiometry * (self.coefficient[1] - self.coefficient[0])
+    }
+}
+
+#[test]
+fn entropic_coefficients_shift_potential_from_reference_temperature() {
+    let graphite: Entropic = Entropic {
+        material: Arc::new(GraphiteSi),
+        coefficient: [3e-4, -1.5e-4],
+    };
+    for x in [0.05, 0.3, 0.6, 0.9] {
+        // The MJ1 fits are temperature independent
+        for material in [&GraphiteSi as &dyn OpenCircuitPotential, &Nmc811] {
+            assert_eq!(material.entropic_coefficient(x), 0.0);
+            assert_eq!(material.potential_at_temperature(x, 308.15), material.potential(x));
+        }
+        let entropic_coefficient: f64 = graphite.entropic_coefficient(x);
+        assert_eq!(graphite.potential_at_temperature(x, 298.15), graphite.potential(x));
+        let shift: f64 = graphite.potential_at_temperature(x, 308.15) - graphite.potential(x);
+        assert!((shift - 10.0 * entropic_coefficient).abs() < 1e-12);
+    }
+}
+
+#[test]
+fn warm_cell_at_rest_has_shifted_voltage() {
+    let negative = || Entropic {
+        material: Arc::new(GraphiteSi),
+        coefficient: [3e-4, -1.5e-4],
+    };
+    let positive = || Entropic {
+        material: Arc::new(Nmc811),
+        coefficient: [-3e-5, -1.3e-4],
+    };
+    let time: Vec<f64> = (0..=10).map(|t| t as f64).collect();
+    let rest = |thermal: Option<ThermalModel>| -> Solution {
+        let mut model = SPMeModel {
+            time_scheme: TimeScheme::CrankNicolson,
+            thermal,
+            ..SPMeModel::default()
+        };
+        model.negative_electrode.open_circuit_potential = Arc::new(negative());
+        model.positive_electrode.open_circuit_potential = Arc::new(positive());
+        model.solve(&time, &vec![0.0; time.len()], &OutputSpecification::default()).unwrap()
+    };
+    let reference: Solution = rest(None);
+    let warm: Solution = rest(Some(ThermalModel {
+        temperature: 318.15,
+        ambient_temperature: 318.15,
+        ..ThermalModel::default()
+    }));
+    let x_n: f64 = reference.negative_electrode.surface_stoichiometry[0];
+    let x_p: f64 = reference.positive_electrode.surface_stoichiometry[0];
+    let shift: f64 = 20.0 * (positive().entropic_coefficient(x_p) - negative().entropic_coefficient(x_n));
+    assert!(shift.abs() > 1e-4);
+    for (warm, reference) in warm.voltage.iter().zip(&reference.voltage) {
+        assert!((warm - reference - shift).abs() < 1e-9, "{warm} vs {reference} + {shift}");
+    }
+}
@@ -0,0 +1,125 @@
+use pxd::model::SPMeModel;
+use pxd::sei::{Sei, SeiGrowth};
+use pxd::spm::SPMModel;
+use pxd::{Error, OutputSpecification, Simulate, Solution};
+
+mod common;
+use common::model;
+
+fn charged_model(sei: Option<Sei>) -> SPMeModel {
+    // Starts from a charged, uniform state so that the negative electrode has lithium to lose
+    let mut model: SPMeModel = model();
+    model.negative_electrode.particle.concentration.fill(0.75 * 34684.0);
+    model.positive_electrode.particle.concentration.fill(0.4 * 50060.0);
+    model.negative_electrode.sei = sei;
+    model
+}
+
+// Accelerated growth, so that an hour shows the fade of months
+const SOLVENT_DIFFUSION_LIMITED: SeiGrowth = SeiGrowth::SolventDiffusionLimited {
+    solvent_diffusivity: 1e-21,
+    solvent_concentration: 2636.0,
+};
+const REACTION_LIMITED: SeiGrowth = SeiGrowth::ReactionLimited {
+    rate_constant: 1e-18,
+    solvent_concentration: 2636.0,
+    open_circuit_potential: 0.4,
+    transfer_coefficient: 0.5,
+};
+const INTERSTITIAL_DIFFUSION_LIMITED: SeiGrowth = SeiGrowth::InterstitialDiffusionLimited {
+    interstitial_diffusivity: 1e-18,
+    interstitial_concentration: 15.0,
+};
+
+#[test]
+fn diffusion_limited_film_grows_with_square_root_of_time_across_calls() {
+    let sei: Sei = Sei::new(SOLVENT_DIFFUSION_LIMITED);
+    let mut model: SPMeModel = charged_model(Some(sei));
+    let first: Vec<f64> = (0..=180).map(|t| t as f64 * 10.0).collect();
+    let second: Vec<f64> = (181..=360).map(|t| t as f64 * 10.0).collect();
+    model.simulate(&first, &vec![0.0; first.len()]);
+    let halfway: f64 = model.negative_electrode.sei.unwrap().thickness;
+    model.simulate(&second, &vec![0.0; second.len()]);
+    let end: f64 = model.negative_electrode.sei.unwrap().thickness;
+
+    // $L^2 = L_0^2 + \frac{2 D c \bar{V}}{z} t$, where the first sample of the first call already takes a step
+    let SeiGrowth::SolventDiffusionLimited { solvent_diffusivity, solvent_concentration } = sei.growth else {
+        unreachable!()
+    };
+    let expected = |t: f64| -> f64 {
+        (sei.thickness.powi(2)
+            + 2.0 * solvent_diffusivity * solvent_concentration * sei.molar_volume / sei.electrons_per_molecule * t)
+            .sqrt()
+    };
+    assert!((halfway / expected(1810.0) - 1.0).abs() < 1e-2, "{halfway} vs {}", expected(1810.0));
+    assert!((end / expected(3610.0) - 1.0).abs() < 1e-2, "{end} vs {}", expected(3610.0));
+}
+
+#[test]
+fn growth_consumes_lithium_of_negative_electrode() {
+    let time: Vec<f64> = (0..=360).map(|t| t as f64 * 10.0).collect();
+    let rest = |model: &mut SPMeModel| -> Solution {
+        model.solve(&time, &vec![0.0; time.len()], &OutputSpecification::default()).unwrap()
+    };
+    let mut reference: SPMeModel = charged_model(None);
+    let expected: Solution = rest(&mut reference);
+
+    for growth in [SOLVENT_DIFFUSION_LIMITED, REACTION_LIMITED, INTERSTITIAL_DIFFUSION_LIMITED] {
+        let mut model: SPMeModel = charged_model(Some(Sei::new(growth)));
+        let solution: Solution = rest(&mut model);
+        let lost: f64 = model.negative_electrode.sei.unwrap().lost_lithium;
+        assert!(lost > 0.0, "{growth:?}");
+
+        // The lithium missing from the negative particles is bound in the film, up to the volume average over
+        // the particle nodes
+        let electrode = &model.negative_electrode;
+        let volume: f64 =
+            electrode.active_material_volume_fraction * electrode.thickness * electrode.height * electrode.width;
+        let missing: f64 = (reference.negative_electrode.particle.average_concentration()
+            - electrode.particle.average_concentration())
+            * volume;
+        assert!((missing / lost - 1.0).abs() < 0.05, "{growth:?}: {missing} vs {lost}");
+
+        // Which leaves the cell at a lower open circuit voltage
+        let n: usize = time.len() - 1;
+        assert!(solution.open_circuit_voltage()[n] < expected.open_circuit_voltage()[n], "{growth:?}");
+    }
+}
+
+#[test]
+fn film_resistance_adds_to_voltage_under_current() {
+    let time: Vec<f64> = (0..=10).map(|t| t as f64).collect();
+    let sei: Sei = Sei {
+        thickness: 1e-7,
+        ..Sei::new(SeiGrowth::reaction_limited())
+    };
+    let charge = |sei: Option<Sei>| -> Solution {
+        charged_model(sei)
+            .solve(&time, &vec![3.4; time.len()], &OutputSpecification::default())
+            .unwrap()
+    };
+    let with_film: Solution = charge(Some(sei));
+    let without_film: Solution = charge(None);
+    for k in 0..time.len() {
+        // Current density per particle surface times the film resistance
+        assert!(with_film.film_overpotential[k] > 1e-3);
+        assert_eq!(without_film.film_overpotential[k], 0.0);
+        let difference: f64 = with_film.voltage[k] - without_film.voltage[k];
+        assert!((difference - with_film.film_overpotential[k]).abs() < 1e-5, "{difference} at {k}");
+    }
+}
+
+#[test]
+fn sei_is_rejected_where_it_is_not_modelled() {
+    let time: Vec<f64> = (0..=10).map(|t| t as f64).collect();
+    let mut positive: SPMeModel = charged_model(None);
+    positive.positive_electrode.sei = Some(Sei::new(SeiGrowth::reaction_limited()));
+    let result = positive.solve(&time, &vec![0.0; time.len()], &OutputSpecification::default());
+    assert!(matches!(result, Err(Error::InvalidParameter { parameter: "SEI", .. })), "{result:?}");
+
+    // The single particle model has no SEI to carry the film and lost lithium over to
+    let mut ageing: SPMeModel = charged_model(Some(Sei::new(SeiGrowth::reaction_limited())));
+    ageing.negative_electrode.double_layer_capacitance = 0.0;
+    ageing.positive_electrode.double_layer_capacitance = 0.0;
+    assert!(matches!(SPMModel::try_from(ageing), Err(Error::InvalidParameter { .. })));
+}
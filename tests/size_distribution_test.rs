@@ -0,0 +1,134 @@
+use pxd::hysteresis::Hysteresis;
+use pxd::model::{Particle, SPMeModel};
+use pxd::ocv::{OpenCircuitPotential, TanhSeries};
+use pxd::size_distribution::{LognormalDistribution, SizeClass};
+use pxd::{Error, Simulate};
+
+use std::sync::Arc;
+
+mod common;
+use common::model;
+
+#[test]
+fn classes_keep_active_material_volume() {
+    let distribution: LognormalDistribution = LognormalDistribution::new(6.1e-6, 2e-6);
+    let classes: Vec<SizeClass> = distribution.classes(7).unwrap();
+    assert_eq!(classes.len(), 7);
+    assert!(classes.windows(2).all(|w| w[0].radius < w[1].radius));
+    assert!((classes.iter().map(|class| class.volume_share).sum::<f64>() - 1.0).abs() < 1e-12);
+    // The middle class sits at the median radius
+    let sigma_squared: f64 = (1.0 + (2.0_f64 / 6.1).powi(2)).ln();
+    assert!((classes[3].radius / (6.1e-6 * (-0.5 * sigma_squared).exp()) - 1.0).abs() < 1e-12);
+
+    let mut model: SPMeModel = model();
+    let volume_fraction: f64 = model.negative_electrode.active_material_volume_fraction;
+    model.negative_electrode.set_size_distribution(&distribution, 7).unwrap();
+    let electrode = &model.negative_electrode;
+    let total: f64 = electrode.active_material_volume_fraction
+        + electrode.blend.iter().map(|material| material.volume_fraction).sum::<f64>();
+    assert!((total - volume_fraction).abs() < 1e-12);
+    assert_eq!(electrode.particles().count(), 7);
+    assert!(electrode.particles().all(|particle| particle.concentration.len() == 20));
+}
+
+#[test]
+fn narrow_distribution_matches_single_particle() {
+    let time: Vec<f64> = (0..=300).map(|t| t as f64 * 2.0).collect();
+    let current: Vec<f64> = vec![3.0; time.len()];
+    let mut single: SPMeModel = model();
+    let mut distributed: SPMeModel = model();
+    distributed
+        .negative_electrode
+        .set_size_distribution(&LognormalDistribution::new(6.1e-6, 1e-9), 5)
+        .unwrap();
+
+    let expected: Vec<f64> = single.simulate(&time, &current);
+    let voltage: Vec<f64> = distributed.simulate(&time, &current);
+    for (v, e) in voltage.iter().zip(&expected) {
+        assert!((v - e).abs() < 1e-5, "{v} vs {e}");
+    }
+}
+
+#[test]
+fn small_particles_fill_first() {
+    let time: Vec<f64> = (0..=300).map(|t| t as f64 * 2.0).collect();
+    let current: Vec<f64> = vec![3.2; time.len()];
+    let mut model: SPMeModel = model();
+    model
+        .negative_electrode
+        .set_size_distribution(&LognormalDistribution::new(6.1e-6, 3e-6), 5)
+        .unwrap();
+    model.simulate(&time, &current);
+
+    // Small particles have more surface per volume, so their stoichiometry rises faster
+    let stoichiometry: Vec<f64> = model
+        .negative_electrode
+        .particles()
+        .map(|particle: &Particle| particle.average_concentration() / particle.concentration_max)
+        .collect();
+    let radius: Vec<f64> = model.negative_electrode.particles().map(|particle| particle.radius).collect();
+    assert!(radius.windows(2).all(|w| w[0] < w[1]));
+    assert!(stoichiometry.windows(2).all(|w| w[0] > w[1]), "{stoichiometry:?}");
+}
+
+#[test]
+fn zero_classes_are_rejected() {
+    let distribution: LognormalDistribution = LognormalDistribution::new(6.1e-6, 2e-6);
+    assert!(matches!(distribution.classes(0), Err(Error::InvalidParameter { .. })));
+    let mut model: SPMeModel = model();
+    let result = model.negative_electrode.set_size_distribution(&distribution, 0);
+    assert!(matches!(result, Err(Error::InvalidParameter { .. })));
+    assert_eq!(model.negative_electrode.particles().count(), 1);
+}
+
+#[test]
+fn invalid_distributions_are_rejected() {
+    for distribution in [
+        LognormalDistribution::new(0.0, 2e-6),
+        LognormalDistribution::new(-6.1e-6, 2e-6),
+        LognormalDistribution::new(f64::NAN, 2e-6),
+        LognormalDistribution::new(6.1e-6, -2e-6),
+        LognormalDistribution::new(6.1e-6, f64::INFINITY),
+    ] {
+        let result = distribution.classes(5);
+        assert!(matches!(result, Err(Error::InvalidParameter { .. })), "{distribution:?}: {result:?}");
+    }
+}
+
+#[test]
+fn second_distribution_is_rejected() {
+    // Applying a distribution again would split the classes of the first into classes of their own
+    let distribution: LognormalDistribution = LognormalDistribution::new(6.1e-6, 2e-6);
+    let mut model: SPMeModel = model();
+    model.negative_electrode.set_size_distribution(&distribution, 5).unwrap();
+    let result = model.negative_electrode.set_size_distribution(&distribution, 5);
+    assert!(matches!(result, Err(Error::InvalidParameter { .. })), "{result:?}");
+    assert_eq!(model.negative_electrode.particles().count(), 5);
+}
+
+#[test]
+fn classes_keep_hysteresis() {
+    // With hysteresis on every class, the classes stay at a common potential and rest does not move lithium
+    let mut lithiation: TanhSeries = TanhSeries::graphite_si();
+    let mut delithiation: TanhSeries = TanhSeries::graphite_si();
+    lithiation.offset -= 0.01;
+    delithiation.offset += 0.01;
+    let branches: (Arc<dyn OpenCircuitPotential>, Arc<dyn OpenCircuitPotential>) =
+        (Arc::new(lithiation), Arc::new(delithiation));
+    let mut single: SPMeModel = model();
+    single.negative_electrode.hysteresis = Some(Hysteresis::new(branches.0, branches.1, 100.0));
+    let mut distributed: SPMeModel = single.clone();
+    distributed
+        .negative_electrode
+        .set_size_distribution(&LognormalDistribution::new(6.1e-6, 1e-9), 5)
+        .unwrap();
+    assert!(distributed.negative_electrode.blend.iter().all(|material| material.hysteresis.is_some()));
+
+    let time: Vec<f64> = (0..=600).map(|t| t as f64 * 2.0).collect();
+    let current: Vec<f64> = time.iter().map(|t| if *t <= 600.0 { 3.0 } else { 0.0 }).collect();
+    let expected: Vec<f64> = single.simulate(&time, &current);
+    let voltage: Vec<f64> = distributed.simulate(&time, &current);
+    for (v, e) in voltage.iter().zip(&expected) {
+        assert!((v - e).abs() < 1e-5, "{v} vs {e}");
+    }
+}
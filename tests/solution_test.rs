@@ -0,0 +1,74 @@
+use pxd::model::{AdaptiveStepping, SPMeModel, TimeStepping};
+use pxd::{OutputSpecification, Simulate, Solution};
+
+mod common;
+use common::model;
+
+fn charge() -> (Vec<f64>, Vec<f64>) {
+    let t: Vec<f64> = (0..=300).map(|step| step as f64 * 2.0).collect();
+    let i: Vec<f64> = vec![3.2; t.len()];
+    (t, i)
+}
+
+#[test]
+fn contributions_add_up_to_voltage() {
+    let (t, i) = charge();
+    let solution: Solution = model().solve(&t, &i, &OutputSpecification::default()).unwrap();
+    assert_eq!(solution.len(), t.len());
+    assert_eq!(solution.voltage, model().simulate(&t, &i));
+
+    let ocv: Vec<f64> = solution.open_circuit_voltage();
+    for (k, ocv) in ocv.iter().enumerate() {
+        let voltage: f64 = ocv
+            - solution.negative_electrode.surface_overpotential[k]
+            - solution.positive_electrode.surface_overpotential[k]
+            + solution.electrolyte_concentration_overpotential[k]
+            + solution.solid_ohmic_overpotential[k]
+            + solution.electrolyte_ohmic_overpotential[k]
+            + solution.contact_overpotential[k];
+        assert!((voltage - solution.voltage[k]).abs() < 1e-12);
+    }
+    assert!(solution.negative_electrode.particle_concentration.is_none());
+    assert!(solution.electrolyte_concentration.is_none());
+}
+
+#[test]
+fn average_stoichiometry_follows_charge_throughput() {
+    let (t, i) = charge();
+    let mut model: SPMeModel = model();
+    let electrode = model.negative_electrode.clone();
+    let solution: Solution = model.solve(&t, &i, &OutputSpecification::default()).unwrap();
+
+    // Lithium inserted into the negative electrode, relative to its capacity
+    let capacity: f64 = electrode.particle.concentration_max
+        * electrode.height
+        * electrode.width
+        * electrode.thickness
+        * electrode.active_material_volume_fraction
+        * 96485.33212331001;
+    let charge: f64 = 3.2 * (t[t.len() - 1] - t[0] + (t[1] - t[0]));
+    let initial: f64 = electrode.particle.concentration_init / electrode.particle.concentration_max;
+    let inserted: f64 = solution.negative_electrode.average_stoichiometry[t.len() - 1] - initial;
+    assert!((inserted / (charge / capacity) - 1.0).abs() < 0.05, "{inserted} vs {}", charge / capacity);
+
+    // The surface runs ahead of the bulk while charging
+    let last: usize = t.len() - 1;
+    assert!(solution.negative_electrode.surface_stoichiometry[last] > solution.negative_electrode.average_stoichiometry[last]);
+}
+
+#[test]
+fn profiles_are_stored_when_requested() {
+    let (t, i) = charge();
+    let mut model: SPMeModel = model();
+    model.time_stepping = TimeStepping::Adaptive(AdaptiveStepping::default());
+    let solution: Solution = model.solve(&t, &i, &OutputSpecification::all()).unwrap();
+
+    let particle = solution.positive_electrode.particle_concentration.as_ref().unwrap();
+    let electrolyte = solution.electrolyte_concentration.as_ref().unwrap();
+    assert_eq!(particle.len(), t.len());
+    assert_eq!(electrolyte.len(), t.len());
+    assert_eq!(particle[0].len(), model.positive_electrode.particle.concentration.len());
+    for (stored, state) in electrolyte[t.len() - 1].iter().zip(&model.electrolyte.concentration) {
+        assert!((stored - state).abs() < 1e-9);
+    }
+}
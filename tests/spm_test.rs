@@ -0,0 +1,79 @@
+use pxd::math::numerical_methods::TimeScheme;
+use pxd::model::{ActiveMaterial, SPMeModel};
+use pxd::sei::{Sei, SeiGrowth};
+use pxd::spm::SPMModel;
+use pxd::{Error, OutputSpecification, Simulate, Solution};
+
+#[test]
+fn runs_at_one_second_steps_close_to_spme_at_low_rate() {
+    let time: Vec<f64> = (0..=1800).map(|t| t as f64).collect();
+    let current: Vec<f64> = vec![0.34; time.len()]; // C/10
+    let mut spme: SPMeModel = SPMeModel {
+        time_scheme: TimeScheme::CrankNicolson,
+        ..SPMeModel::default()
+    };
+    spme.negative_electrode.double_layer_capacitance = 0.0;
+    spme.positive_electrode.double_layer_capacitance = 0.0;
+    let expected: Solution = spme.clone().solve(&time, &current, &OutputSpecification::default()).unwrap();
+    let voltage: Vec<f64> = SPMModel::try_from(spme).unwrap().simulate(&time, &current);
+    for (k, v) in voltage.iter().enumerate() {
+        // Once the electrolyte polarisation is taken out, only its small effect on the kinetics remains
+        let e: f64 = expected.voltage[k]
+            - expected.electrolyte_concentration_overpotential[k]
+            - expected.electrolyte_ohmic_overpotential[k];
+        assert!((v - e).abs() < 1e-3, "{v} vs {e} at {k}");
+    }
+}
+
+#[test]
+fn voltage_has_no_electrolyte_contributions() {
+    let time: Vec<f64> = (0..=600).map(|t| t as f64).collect();
+    let solution: Solution = SPMModel::default()
+        .solve(&time, &vec![3.4; time.len()], &OutputSpecification::all())
+        .unwrap();
+    let ocv: Vec<f64> = solution.open_circuit_voltage();
+    for (k, ocv) in ocv.iter().enumerate() {
+        let voltage: f64 = ocv
+            - solution.negative_electrode.surface_overpotential[k]
+            - solution.positive_electrode.surface_overpotential[k]
+            + solution.solid_ohmic_overpotential[k]
+            + solution.contact_overpotential[k];
+        assert!((voltage - solution.voltage[k]).abs() < 1e-12);
+        assert_eq!(solution.electrolyte_concentration_overpotential[k], 0.0);
+        assert_eq!(solution.electrolyte_ohmic_overpotential[k], 0.0);
+    }
+}
+
+#[test]
+fn forward_euler_is_limited_by_particle_diffusion() {
+    let mut model: SPMModel = SPMModel {
+        time_scheme: TimeScheme::ForwardTimeCenteredSpace,
+        ..SPMModel::default()
+    };
+    let result = model.try_simulate(&[0.0, 1.0, 2.0], &[1.0; 3]);
+    assert!(matches!(result, Err(Error::UnstableScheme { component: "negative particle", .. })), "{result:?}");
+    assert!(model.try_simulate(&[0.0, 0.25, 0.5], &[1.0; 3]).is_ok());
+}
+
+#[test]
+fn unsupported_parts_set_after_conversion_are_rejected() {
+    // The electrodes are public, so the checks of the conversion are repeated when the model is run
+    let mut double_layer: SPMModel = SPMModel::default();
+    double_layer.negative_electrode.double_layer_capacitance = 0.2;
+    let mut blend: SPMModel = SPMModel::default();
+    let material: ActiveMaterial = ActiveMaterial {
+        particle: blend.positive_electrode.particle.clone(),
+        open_circuit_potential: blend.positive_electrode.open_circuit_potential.clone(),
+        hysteresis: None,
+        volume_fraction: 0.01,
+        reaction_rate_constant: blend.positive_electrode.reaction_rate_constant,
+    };
+    blend.positive_electrode.active_material_volume_fraction -= 0.01;
+    blend.positive_electrode.blend.push(material);
+    let mut sei: SPMModel = SPMModel::default();
+    sei.negative_electrode.sei = Some(Sei::new(SeiGrowth::reaction_limited()));
+    for mut spm in [double_layer, blend, sei] {
+        let result = spm.try_simulate(&[0.0, 1.0], &[1.0, 1.0]);
+        assert!(matches!(result, Err(Error::InvalidParameter { parameter: "SPM model", .. })), "{result:?}");
+    }
+}
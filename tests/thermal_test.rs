@@ -0,0 +1,65 @@
+use pxd::model::SPMeModel;
+use pxd::thermal::ThermalModel;
+use pxd::{OutputSpecification, Simulate, Solution};
+
+mod common;
+use common::model;
+
+fn thermal_model(thermal: Option<ThermalModel>) -> SPMeModel {
+    SPMeModel {
+        thermal,
+        ..model()
+    }
+}
+
+#[test]
+fn heats_up_under_load_and_stays_isothermal_without_thermal_model() {
+    let time: Vec<f64> = (0..=60).map(|t| t as f64 * 10.0).collect();
+    let current: Vec<f64> = vec![3.4; time.len()]; // 1C charge
+    let solution: Solution = thermal_model(Some(ThermalModel::default()))
+        .solve(&time, &current, &OutputSpecification::default())
+        .unwrap();
+    assert!(solution.temperature.windows(2).all(|w| w[1] > w[0]), "{:?}", solution.temperature);
+    assert!(solution.temperature[time.len() - 1] < 298.15 + 10.0);
+
+    let solution: Solution = thermal_model(None).solve(&time, &current, &OutputSpecification::default()).unwrap();
+    assert!(solution.temperature.iter().all(|t| *t == 298.15));
+}
+
+#[test]
+fn relaxes_to_ambient_at_rest() {
+    let thermal: ThermalModel = ThermalModel {
+        temperature: 318.15,
+        ..ThermalModel::default()
+    };
+    let time: Vec<f64> = (0..=360).map(|t| t as f64 * 10.0).collect();
+    let solution: Solution = thermal_model(Some(thermal))
+        .solve(&time, &vec![0.0; time.len()], &OutputSpecification::default())
+        .unwrap();
+
+    // Newton cooling with time constant $C / (h A)$, where the first sample already takes a step
+    let tau: f64 = thermal.heat_capacity / (thermal.heat_transfer_coefficient * thermal.cooling_area);
+    for (t, temperature) in time.iter().zip(&solution.temperature) {
+        let expected: f64 = thermal.ambient_temperature + 20.0 * (-(t + 10.0) / tau).exp();
+        assert!((temperature - expected).abs() < 0.1, "{temperature} vs {expected} at {t}");
+    }
+}
+
+#[test]
+fn warm_cell_has_lower_overpotentials() {
+    let warm: ThermalModel = ThermalModel {
+        temperature: 318.15,
+        ambient_temperature: 318.15,
+        ..ThermalModel::default()
+    };
+    let time: Vec<f64> = (0..=30).map(|t| t as f64 * 2.0).collect();
+    let current: Vec<f64> = vec![3.4; time.len()];
+    let polarisation = |thermal: Option<ThermalModel>| -> f64 {
+        let solution: Solution = thermal_model(thermal).solve(&time, &current, &OutputSpecification::default()).unwrap();
+        let n: usize = solution.len() - 1;
+        solution.voltage[n] - solution.open_circuit_voltage()[n]
+    };
+    let cold: f64 = polarisation(None);
+    let warm: f64 = polarisation(Some(warm));
+    assert!(warm > 0.0 && warm < 0.8 * cold, "{warm} vs {cold}");
+}